@@ -1,21 +1,113 @@
 use std::{
     env,
     fs::File,
-    io::{BufReader, Read},
+    io::{self, BufReader, IsTerminal, Read},
+    process,
 };
 
+use anyhow::{bail, Context};
+
+mod repl;
+
+const USAGE: &str = "Usage: aqa-cli [--seed <n>] [--color <auto|always|never>] [--dump-tokens] [--dump-ast] [--trace] [--backend <tree|bytecode>] [--check-types] [file]";
+
 fn main() -> anyhow::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() == 2 {
-        let path = &args[1];
-        let file = File::open(path)?;
+    let mut args = env::args().skip(1);
+
+    let mut options = aqa::RunOptions::default();
+    let mut color = None;
+    let mut path = None;
+    // Options the REPL has no use for
+    let mut file_only = None;
+    while let Some(arg) = args.next() {
+        if [
+            "--dump-tokens",
+            "--dump-ast",
+            "--trace",
+            "--check-types",
+            "--backend",
+        ]
+        .contains(&arg.as_str())
+        {
+            file_only.get_or_insert(arg.clone());
+        }
+
+        if arg == "--seed" {
+            let value = args.next().context(USAGE)?;
+            options.seed = Some(value.parse::<u64>().context("--seed expects an integer")?);
+        } else if arg == "--color" {
+            color = match args.next().context(USAGE)?.as_str() {
+                "always" => Some(aqa::ColorMode::Ansi),
+                "never" => Some(aqa::ColorMode::Plain),
+                "auto" => None,
+                _ => bail!("--color expects one of auto, always or never"),
+            };
+        } else if arg == "--dump-tokens" {
+            options.dump_tokens = true;
+        } else if arg == "--dump-ast" {
+            options.dump_ast = true;
+        } else if arg == "--trace" {
+            options.trace = true;
+        } else if arg == "--check-types" {
+            options.check_types = true;
+        } else if arg == "--backend" {
+            options.backend = match args.next().context(USAGE)?.as_str() {
+                "tree" => aqa::Backend::TreeWalker,
+                "bytecode" => aqa::Backend::Bytecode,
+                _ => bail!("--backend expects one of tree or bytecode"),
+            };
+        } else if path.is_none() {
+            path = Some(arg);
+        } else {
+            bail!(USAGE);
+        }
+    }
+
+    let color = color.unwrap_or(if io::stderr().is_terminal() {
+        aqa::ColorMode::Ansi
+    } else {
+        aqa::ColorMode::Plain
+    });
+
+    if let Some(path) = path {
+        let file = File::open(&path)?;
         let mut buf_reader = BufReader::new(file);
         let mut contents = String::new();
         buf_reader.read_to_string(&mut contents)?;
 
-        aqa::run(contents)?;
+        match aqa::run(
+            contents.clone(),
+            &mut aqa::StdoutSink,
+            &mut aqa::StdinSource,
+            &options,
+        ) {
+            Ok(output) => {
+                for token in output.tokens.unwrap_or_default() {
+                    eprintln!("{:?}", token);
+                }
+                if let Some(ast) = output.ast {
+                    eprintln!("{}", ast);
+                }
+                for line in output.trace.unwrap_or_default() {
+                    eprintln!("trace: {}", line);
+                }
+            }
+            Err(error) => {
+                let diagnostics = match error {
+                    aqa::Error::ParserError(_) => aqa::check(contents.clone()),
+                    aqa::Error::CheckerError(_) => aqa::check_types(contents.clone()),
+                    error => vec![aqa::Diagnostic::from(&error)],
+                };
+                for diagnostic in diagnostics {
+                    eprintln!("{}", diagnostic.render(&path, &contents, color));
+                }
+                process::exit(1);
+            }
+        }
+    } else if let Some(option) = file_only {
+        bail!("{} can only be used when running a file", option);
     } else {
-        println!("Usage: aqa-cli <file>");
+        repl::run(options.seed, color)?;
     }
     Ok(())
 }
@@ -0,0 +1,206 @@
+use std::fs;
+
+use aqa::{ColorMode, Interpreter, TokenType};
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+const PROMPT: &str = ">>> ";
+const CONTINUATION_PROMPT: &str = "... ";
+const SOURCE_NAME: &str = "<repl>";
+
+const HELP: &str = "\
+Enter AQA pseudocode to run it. Blocks are run once they are closed.
+
+:vars         list global variables
+:reset        forget every variable, subroutine and record
+:load <file>  run a file, keeping what it defines
+:help         show this message
+:quit         leave the REPL";
+
+// Every entry is kept in one growing source, as a subroutine defined by an
+// earlier entry can fail long after it was entered
+#[derive(Default)]
+struct History {
+    source: String,
+    // (name, start offset) of each entry
+    entries: Vec<(String, usize)>,
+}
+
+impl History {
+    // Returns where the entry starts within the source
+    fn push(&mut self, name: &str, source: &str) -> usize {
+        let start = self.source.len();
+        self.source += source;
+        if !self.source.ends_with('\n') {
+            self.source.push('\n');
+        }
+        self.entries.push((name.to_string(), start));
+        start
+    }
+
+    fn name_at(&self, offset: usize) -> &str {
+        self.entries
+            .iter()
+            .rev()
+            .find(|(_, start)| *start <= offset)
+            .map_or(SOURCE_NAME, |(name, _)| name)
+    }
+
+    fn clear(&mut self) {
+        self.source.clear();
+        self.entries.clear();
+    }
+}
+
+pub fn run(seed: Option<u64>, color: ColorMode) -> anyhow::Result<()> {
+    let mut editor = DefaultEditor::new()?;
+    let mut output = aqa::StdoutSink;
+    let mut input = aqa::StdinSource;
+    let mut interpreter = Interpreter::new(&mut output, &mut input).with_seed(seed);
+    let mut history = History::default();
+
+    println!("AQA pseudocode REPL, type :help for help");
+
+    let mut buffer = String::new();
+    loop {
+        let prompt = if buffer.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                buffer.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(error) => return Err(error.into()),
+        };
+
+        if buffer.is_empty() {
+            if let Some(command) = line.trim().strip_prefix(':') {
+                editor.add_history_entry(line.as_str())?;
+                if !meta_command(command, &mut interpreter, &mut history, color) {
+                    break;
+                }
+                continue;
+            }
+        }
+
+        buffer += &line;
+        buffer.push('\n');
+
+        if open_blocks(&buffer) > 0 {
+            continue;
+        }
+
+        editor.add_history_entry(buffer.trim_end())?;
+        let source = std::mem::take(&mut buffer);
+        evaluate(
+            SOURCE_NAME,
+            &source,
+            &mut interpreter,
+            &mut history,
+            color,
+            true,
+        );
+    }
+
+    Ok(())
+}
+
+// Returns false when the REPL should exit
+fn meta_command(
+    command: &str,
+    interpreter: &mut Interpreter,
+    history: &mut History,
+    color: ColorMode,
+) -> bool {
+    let (name, argument) = match command.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (command, ""),
+    };
+
+    match name {
+        "vars" => {
+            for (name, value) in interpreter.globals() {
+                println!("{} = {}", name, value);
+            }
+        }
+        "reset" => {
+            interpreter.reset();
+            history.clear();
+        }
+        "load" if !argument.is_empty() => match fs::read_to_string(argument) {
+            Ok(contents) => evaluate(argument, &contents, interpreter, history, color, false),
+            Err(error) => eprintln!("Cannot read '{}': {}", argument, error),
+        },
+        "load" => eprintln!("Usage: :load <file>"),
+        "help" => println!("{}", HELP),
+        "quit" | "exit" => return false,
+        _ => eprintln!("Unknown command ':{}', type :help for help", name),
+    }
+
+    true
+}
+
+fn evaluate(
+    path: &str,
+    source: &str,
+    interpreter: &mut Interpreter,
+    history: &mut History,
+    color: ColorMode,
+    show_value: bool,
+) {
+    let start = history.push(path, source);
+    let result = aqa::parse_from(history.source.clone(), start)
+        .and_then(|program| interpreter.execute(&program));
+
+    match result {
+        Ok(Some(value)) if show_value => println!("{}", value),
+        Ok(_) => {}
+        Err(error) => {
+            let diagnostics = match error {
+                aqa::Error::ParserError(_) => aqa::check_from(history.source.clone(), start),
+                error => vec![aqa::Diagnostic::from(&error)],
+            };
+            for diagnostic in diagnostics {
+                let path = history.name_at(diagnostic.span.start().offset());
+                eprintln!("{}", diagnostic.render(path, &history.source, color));
+            }
+        }
+    }
+}
+
+// Counts blocks that have been opened but not yet closed, so the REPL knows
+// to keep reading lines. `ELSE IF` continues the enclosing IF block.
+fn open_blocks(source: &str) -> i64 {
+    let tokens = match aqa::scan(source.to_string()) {
+        Ok(tokens) => tokens,
+        Err(_) => return 0,
+    };
+
+    let mut depth = 0;
+    let mut previous = None;
+    for token in &tokens {
+        match token.token_type {
+            TokenType::If if previous != Some(&TokenType::Else) => depth += 1,
+            TokenType::While
+            | TokenType::For
+            | TokenType::Repeat
+            | TokenType::Subroutine
+            | TokenType::Record => depth += 1,
+            TokenType::EndIf
+            | TokenType::EndWhile
+            | TokenType::EndFor
+            | TokenType::Until
+            | TokenType::EndSubroutine
+            | TokenType::EndRecord => depth -= 1,
+            _ => {}
+        }
+        previous = Some(&token.token_type);
+    }
+
+    depth
+}
@@ -0,0 +1,247 @@
+use std::{collections::VecDeque, fs, path::Path};
+
+use aqa::{Backend, RunOptions};
+
+fn run(source: &str, backend: Backend) -> (Vec<String>, String) {
+    let mut output = Vec::new();
+    let mut input = VecDeque::from([String::from("42")]);
+    let options = RunOptions {
+        trace: true,
+        seed: Some(0),
+        backend,
+        ..RunOptions::default()
+    };
+
+    let result = match aqa::run(source.to_string(), &mut output, &mut input, &options) {
+        Ok(run) => format!("{:?} {:?}", run.value, run.trace),
+        Err(error) => error.to_string(),
+    };
+    (output, result)
+}
+
+fn assert_backends_agree(name: &str, source: &str) {
+    assert_eq!(
+        run(source, Backend::TreeWalker),
+        run(source, Backend::Bytecode),
+        "backends disagree on {}",
+        name
+    );
+}
+
+// The output, then the error or the value of the last expression, as kept in
+// the `.out` file next to each program
+fn transcript(source: &str, backend: Backend) -> String {
+    let mut output = Vec::new();
+    let mut input = VecDeque::from([String::from("42")]);
+    let options = RunOptions {
+        seed: Some(0),
+        backend,
+        ..RunOptions::default()
+    };
+
+    let mut lines = Vec::new();
+    match aqa::run(source.to_string(), &mut output, &mut input, &options) {
+        Ok(run) => {
+            lines = run.output;
+            if let Some(value) = run.value {
+                lines.push(format!("value: {}", value));
+            }
+        }
+        Err(error) => {
+            lines.append(&mut output);
+            lines.push(format!("error: {}", error));
+        }
+    }
+
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+fn programs(directory: &Path, found: &mut Vec<String>) {
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            programs(&path, found);
+        } else if path.extension().is_some_and(|extension| extension == "aqa") {
+            found.push(path.to_string_lossy().into_owned());
+        }
+    }
+}
+
+#[test]
+fn corpus() {
+    let mut found = Vec::new();
+    programs(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests"),
+        &mut found,
+    );
+    assert!(!found.is_empty());
+
+    for path in found {
+        let source = fs::read_to_string(&path).unwrap();
+        assert_backends_agree(&path, &source);
+
+        let expected = fs::read_to_string(Path::new(&path).with_extension("out"))
+            .unwrap_or_else(|_| panic!("{} has no expected output", path));
+        for backend in [Backend::TreeWalker, Backend::Bytecode] {
+            assert_eq!(
+                transcript(&source, backend),
+                expected,
+                "unexpected output from {} with {:?}",
+                path,
+                backend
+            );
+        }
+    }
+}
+
+// The type checker must not reject any program that runs without errors
+#[test]
+fn checker_accepts_corpus() {
+    let mut found = Vec::new();
+    programs(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests"),
+        &mut found,
+    );
+
+    for path in found {
+        let source = fs::read_to_string(&path).unwrap();
+        if transcript(&source, Backend::TreeWalker).contains("error: ") {
+            continue;
+        }
+        let errors: Vec<String> = aqa::check_types(source)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+        assert!(errors.is_empty(), "{} was rejected: {:?}", path, errors);
+    }
+}
+
+#[test]
+fn runtime_errors() {
+    let programs = [
+        (
+            "SUBROUTINE f(n)\n\tRETURN f(n + 1)\nENDSUBROUTINE\nf(0)\n",
+            "Stack overflow when calling 'f'",
+        ),
+        (
+            "CONSTANT x <- 1\nSUBROUTINE f()\n\tx <- 2\nENDSUBROUTINE\nf()\n",
+            "Cannot reassign constant 'x'",
+        ),
+        (
+            "SUBROUTINE f()\nENDSUBROUTINE\nx <- f() + 1\n",
+            "'f' did not return a value",
+        ),
+        (
+            "SUBROUTINE f(a, b)\nENDSUBROUTINE\nf(1)\n",
+            "'f' takes 2 argument(s) but 1 were given",
+        ),
+        ("OUTPUT undefined(1)\n", "Undefined subroutine 'undefined'"),
+        ("OUTPUT y\n", "Undefined variable 'y'"),
+        (
+            "FOR i <- 0 TO 10 STEP 0\nENDFOR\n",
+            "FOR loop STEP cannot be 0",
+        ),
+        ("FOR i IN 5\nENDFOR\n", "Cannot iterate over 'Integer'"),
+        (
+            "IF 1 THEN\nENDIF\n",
+            "Condition must be a Boolean, found 'Integer'",
+        ),
+        ("OUTPUT True AND 1\n", "Mismatched Type"),
+        ("OUTPUT 1 DIV 0\n", "Division by zero"),
+        (
+            "x <- [1]\nx['a'] <- 1\n",
+            "Expected an Integer, found 'String'",
+        ),
+        ("x <- 1\nx.field <- 2\n", "'Integer' is not a record"),
+        (
+            "OUTPUT LEN(1, 2)\n",
+            "'LEN' takes 1 argument(s) but 2 were given",
+        ),
+        ("OUTPUT USERINPUT + 1\n", "Mismatched Type"),
+        (
+            "x : Integer <- 1\nx <- 'a'\n",
+            "Expected Integer for 'x', found String",
+        ),
+        (
+            "SUBROUTINE f(a : Integer) : Integer\n\tRETURN a / 2.0\nENDSUBROUTINE\nOUTPUT f(1)\n",
+            "Expected Integer for 'f', found Real",
+        ),
+        (
+            "SUBROUTINE f(a : Integer)\nENDSUBROUTINE\nf('s')\n",
+            "Expected Integer for 'a', found String",
+        ),
+        (
+            "RECORD P\n\ta : Real\nENDRECORD\np <- P(1)\np.a <- 'x'\n",
+            "Expected Real for 'a', found String",
+        ),
+        (
+            "SUBROUTINE f()\n\tv : Boolean\n\tOUTPUT v\nENDSUBROUTINE\nv <- 1\nf()\n",
+            "Undefined variable 'v'",
+        ),
+    ];
+
+    for (source, message) in programs {
+        assert_backends_agree(source, source);
+        let (_, result) = run(source, Backend::TreeWalker);
+        assert!(
+            result.ends_with(message),
+            "expected '{}' from {:?}, found '{}'",
+            message,
+            source,
+            result
+        );
+    }
+}
+
+#[test]
+fn seeded_random_numbers() {
+    assert_backends_agree("random", "OUTPUT RANDOM_INT(1, 100), RANDOM_INT(1, 100)\n");
+}
+
+#[test]
+fn random_numbers_stay_within_their_bounds() {
+    let source = "FOR i <- 1 TO 200\n\tOUTPUT RANDOM_INT(3, 5)\nENDFOR\nOUTPUT RANDOM_INT(7, 7)\n";
+
+    for backend in [Backend::TreeWalker, Backend::Bytecode] {
+        let (output, _) = run(source, backend);
+        let (range, single) = output.split_at(200);
+        assert!(
+            range.iter().all(|n| ["3", "4", "5"].contains(&n.as_str())),
+            "{:?} went out of bounds: {:?}",
+            backend,
+            range
+        );
+        for n in ["3", "4", "5"] {
+            assert!(
+                range.iter().any(|m| m == n),
+                "{:?} never gave {}",
+                backend,
+                n
+            );
+        }
+        assert_eq!(single, ["7"]);
+    }
+}
+
+#[test]
+fn inverted_random_range() {
+    for backend in [Backend::TreeWalker, Backend::Bytecode] {
+        let (_, result) = run("OUTPUT RANDOM_INT(5, 1)\n", backend);
+        assert!(
+            result.ends_with("Invalid range: 5 is greater than 1"),
+            "{:?} reported '{}'",
+            backend,
+            result
+        );
+    }
+}
+
+#[test]
+fn same_seed_same_numbers() {
+    let source = "FOR i <- 1 TO 20\n\tOUTPUT RANDOM_INT(1, 1000)\nENDFOR\n";
+
+    for backend in [Backend::TreeWalker, Backend::Bytecode] {
+        assert_eq!(run(source, backend), run(source, backend));
+    }
+    assert_backends_agree("a seeded sequence", source);
+}
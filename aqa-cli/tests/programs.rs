@@ -0,0 +1,35 @@
+use std::{collections::VecDeque, fs, path::Path};
+
+use aqa::{Backend, RunOptions};
+
+fn run_file(name: &str, backend: Backend) -> Vec<String> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(name);
+    let source = fs::read_to_string(path).unwrap();
+
+    let mut output = Vec::new();
+    let mut input = VecDeque::new();
+    let options = RunOptions {
+        backend,
+        ..RunOptions::default()
+    };
+    aqa::run(source, &mut output, &mut input, &options).unwrap();
+    output
+}
+
+#[test]
+fn fizzbuzz() {
+    let expected: Vec<String> = (1..=100)
+        .map(|i| match (i % 3, i % 5) {
+            (0, 0) => String::from("FizzBuzz"),
+            (0, _) => String::from("Fizz"),
+            (_, 0) => String::from("Buzz"),
+            _ => i.to_string(),
+        })
+        .collect();
+
+    for backend in [Backend::TreeWalker, Backend::Bytecode] {
+        assert_eq!(run_file("fizzbuzz.aqa", backend), expected);
+    }
+}
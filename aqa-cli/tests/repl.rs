@@ -0,0 +1,64 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+fn repl(input: &str) -> (String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_aqa-cli"))
+        .args(["--color", "never"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn errors_point_into_earlier_entries() {
+    let (_, errors) = repl(
+        "SUBROUTINE f()\n\tRETURN 1 + 'a'\nENDSUBROUTINE\nOUTPUT f() + LEN('ééééééééé')\nOUTPUT 1 2\n",
+    );
+
+    assert_eq!(
+        errors,
+        "error: Mismatched Type\n --> <repl>:2:9\n  |\n2 | \tRETURN 1 + 'a'\n  | \t       ^^^^^^^\n\n\
+         error: Expected end of line, found '2'\n --> <repl>:1:10\n  |\n1 | OUTPUT 1 2\n  |          ^\n  = help: each statement must start on a new line\n\n"
+    );
+}
+
+#[test]
+fn state_carries_between_entries() {
+    let (output, _) =
+        repl("x <- 2\nSUBROUTINE double(n)\n\tRETURN n * 2\nENDSUBROUTINE\nOUTPUT double(x)\n");
+    assert!(output.ends_with("4\n"), "{}", output);
+}
+
+#[test]
+fn options_for_files_are_rejected() {
+    for option in ["--dump-tokens", "--dump-ast", "--trace", "--check-types"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_aqa-cli"))
+            .arg(option)
+            .env("RUST_BACKTRACE", "0")
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+
+        assert!(!output.status.success());
+        assert_eq!(
+            String::from_utf8(output.stderr).unwrap(),
+            format!("Error: {} can only be used when running a file\n", option)
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+fn program(lines: usize) -> String {
+    let mut contents = String::new();
+    for i in 0..lines / 4 {
+        contents += &format!("total <- total + {} * 2.5 MOD 3\n", i);
+        contents += "IF total >= 100 AND NOT done THEN\n";
+        contents += "\tOUTPUT 'Reached the limit: ', total\n";
+        contents += "ENDIF\n";
+    }
+    contents
+}
+
+fn scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scan");
+    for lines in [1_000, 4_000, 16_000] {
+        let contents = program(lines);
+        group.throughput(Throughput::Bytes(contents.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(lines),
+            &contents,
+            |b, contents| b.iter(|| aqa::scan(contents.clone()).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, scan);
+criterion_main!(benches);
@@ -3,13 +3,38 @@ use std::{env, fs, io, path::Path};
 fn main() -> io::Result<()> {
     let mut contents = String::new();
 
-    let variants = ["Int", "Float"];
+    // (left variant, right variant, left operand, right operand, result variant)
+    let numeric_pairs = [
+        ("Float", "Float", "left_value", "right_value", "Float"),
+        (
+            "Int",
+            "Float",
+            "(left_value as f64)",
+            "right_value",
+            "Float",
+        ),
+        (
+            "Float",
+            "Int",
+            "left_value",
+            "(right_value as f64)",
+            "Float",
+        ),
+    ];
     let operators = [
         ("Add", "+"),
         ("Subtract", "-"),
         ("Multiply", "*"),
         ("Divide", "/"),
     ];
+    // Integer arithmetic reports overflow instead of wrapping or panicking
+    let integer_operators = [
+        ("Add", "checked_add"),
+        ("Subtract", "checked_sub"),
+        ("Multiply", "checked_mul"),
+        ("IntDivide", "checked_div"),
+        ("Modulus", "checked_rem"),
+    ];
 
     let comparisons = [
         ("LessThan", "<"),
@@ -17,35 +42,146 @@ fn main() -> io::Result<()> {
         ("GreaterThan", ">"),
         ("GreaterThanOrEqualTo", ">="),
     ];
+    let equalities = [("EqualTo", "=="), ("NotEqualTo", "!=")];
 
     let mut combinations = Vec::new();
 
-    for variant in variants {
+    for (left_variant, right_variant, left, right, result) in numeric_pairs {
         for (operator_variant, operator_char) in operators {
-            combinations.push((variant, variant, operator_variant, operator_char));
+            combinations.push((
+                left_variant,
+                right_variant,
+                operator_variant,
+                result,
+                format!("{} {} {}", left, operator_char, right),
+            ));
         }
-        for (comparison_variant, comparison_char) in comparisons {
-            combinations.push((variant, "Bool", comparison_variant, comparison_char))
+        for (comparison_variant, comparison_char) in comparisons.iter().chain(&equalities) {
+            combinations.push((
+                left_variant,
+                right_variant,
+                comparison_variant,
+                "Bool",
+                format!("{} {} {}", left, comparison_char, right),
+            ));
         }
-        combinations.push((variant, "Bool", "EqualTo", "=="));
-        combinations.push((variant, "Bool", "NotEqualTo", "!="));
     }
 
-    combinations.push(("String", "String", "Add", "+ &"));
+    // '/' is always real division; DIV is the Integer one
+    combinations.push((
+        "Int",
+        "Int",
+        "Divide",
+        "Float",
+        String::from("left_value as f64 / right_value as f64"),
+    ));
+    for (comparison_variant, comparison_char) in comparisons.iter().chain(&equalities) {
+        combinations.push((
+            "Int",
+            "Int",
+            comparison_variant,
+            "Bool",
+            format!("left_value {} right_value", comparison_char),
+        ));
+    }
+
+    for variant in ["Bool", "String"] {
+        for (equality_variant, equality_char) in equalities {
+            combinations.push((
+                variant,
+                variant,
+                equality_variant,
+                "Bool",
+                format!("left_value {} right_value", equality_char),
+            ));
+        }
+    }
+
+    for (comparison_variant, comparison_char) in comparisons.iter().chain(&equalities) {
+        combinations.push((
+            "Char",
+            "Char",
+            comparison_variant,
+            "Bool",
+            format!("left_value {} right_value", comparison_char),
+        ));
+    }
+
+    // A Char equals the String holding just that character
+    for (equality_variant, equality_char) in equalities {
+        combinations.push((
+            "Char",
+            "String",
+            equality_variant,
+            "Bool",
+            format!("left_value.to_string() {} right_value", equality_char),
+        ));
+        combinations.push((
+            "String",
+            "Char",
+            equality_variant,
+            "Bool",
+            format!("left_value {} right_value.to_string()", equality_char),
+        ));
+    }
+
+    combinations.push((
+        "String",
+        "String",
+        "Add",
+        "String",
+        String::from("left_value + &right_value"),
+    ));
+    for (left_variant, right_variant) in [("String", "Char"), ("Char", "String"), ("Char", "Char")]
+    {
+        combinations.push((
+            left_variant,
+            right_variant,
+            "Add",
+            "String",
+            String::from("format!(\"{}{}\", left_value, right_value)"),
+        ));
+    }
+
+    // The checker's `Type` variants share their names with `Value`'s, so its
+    // operator table is generated from the same combinations
+    let mut types = String::from("match (left, operator, right) {\n");
+    for (operator_variant, _) in integer_operators {
+        types += &format!(
+            "\t(Type::Int, TokenType::{}, Type::Int) => Some(Type::Int),\n",
+            operator_variant
+        );
+    }
+    for (left_variant, right_variant, operator_variant, result, _) in &combinations {
+        types += &format!(
+            "\t(Type::{}, TokenType::{}, Type::{}) => Some(Type::{}),\n",
+            left_variant, operator_variant, right_variant, result
+        );
+    }
+    types += "\t_ => None,\n}\n";
 
-    contents += "match (left_value, operator.token_type, right_value) {\n";
+    contents += "match (left_value, operator, right_value) {\n";
+
+    contents += "\t(Value::Int(_), TokenType::Divide | TokenType::IntDivide | TokenType::Modulus, Value::Int(0)) => Err(Error::new(ErrorType::DivisionByZero, right_span)),\n";
+
+    for (operator_variant, method) in integer_operators {
+        contents += &format!(
+            "\t(Value::Int(left_value), TokenType::{}, Value::Int(right_value)) => left_value.{}(right_value).map(Value::Int).ok_or(Error::new(ErrorType::IntegerOverflow, span)),\n",
+            operator_variant, method,
+        );
+    }
 
-    for (variant, result, operator_variant, operator_char) in combinations {
+    for (left_variant, right_variant, operator_variant, result, expression) in combinations {
         contents += &format!(
-            "\t(Value::{}(left_value), TokenType::{}, Value::{}(right_value)) => Ok(Value::{}(left_value {} right_value)),\n",
-            variant, operator_variant, variant, result, operator_char,
+            "\t(Value::{}(left_value), TokenType::{}, Value::{}(right_value)) => Ok(Value::{}({})),\n",
+            left_variant, operator_variant, right_variant, result, expression,
         );
     }
 
-    contents += "\t_ => Err(Error::new(ErrorType::MismatchedType))
+    contents += "\t_ => Err(Error::new(ErrorType::MismatchedType, span))
 }\n";
 
     let out_dir = env::var_os("OUT_DIR").unwrap();
-    let dest_path = Path::new(&out_dir).join("binary.rs");
-    fs::write(dest_path, contents)
+    fs::write(Path::new(&out_dir).join("binary.rs"), contents)?;
+    fs::write(Path::new(&out_dir).join("binary_types.rs"), types)
 }
@@ -0,0 +1,83 @@
+use std::fmt;
+
+use crate::Span;
+
+#[derive(Debug)]
+pub enum ErrorType {
+    MismatchedOperands(String, String, String),
+    MismatchedOperand(String, String),
+    ExpectedBool(String),
+    NonBoolCondition(String),
+    ExpectedInt(String),
+    NotIterable(String),
+    NotIndexable(String),
+    NotARecord(String),
+    UnknownField(String, String),
+    ArityMismatch(String, usize, usize),
+    MismatchedArgument(String, String),
+    DeclaredType(String, String, String),
+    UnknownType(String),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub error_type: ErrorType,
+    pub span: Span,
+}
+
+impl Error {
+    pub fn new(error_type: ErrorType, span: Span) -> Self {
+        Self { error_type, span }
+    }
+}
+
+impl fmt::Display for ErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            ErrorType::MismatchedOperands(operator, left, right) => {
+                format!("Cannot apply {} to {} and {}", operator, left, right)
+            }
+            ErrorType::MismatchedOperand(operator, right) => {
+                format!("Cannot apply {} to {}", operator, right)
+            }
+            ErrorType::ExpectedBool(found) => format!("Expected a Boolean, found {}", found),
+            ErrorType::NonBoolCondition(found) => {
+                format!("Condition must be a Boolean, found {}", found)
+            }
+            ErrorType::ExpectedInt(found) => format!("Expected an Integer, found {}", found),
+            ErrorType::NotIterable(found) => format!("Cannot iterate over {}", found),
+            ErrorType::NotIndexable(found) => format!("Cannot index into {}", found),
+            ErrorType::NotARecord(found) => format!("{} is not a record", found),
+            ErrorType::UnknownField(record, field) => {
+                format!("Record '{}' has no field '{}'", record, field)
+            }
+            ErrorType::ArityMismatch(name, expected, found) => format!(
+                "'{}' takes {} argument(s) but {} were given",
+                name, expected, found
+            ),
+            ErrorType::MismatchedArgument(name, found) => {
+                format!("'{}' cannot accept {}", name, found)
+            }
+            ErrorType::DeclaredType(name, declared, found) => {
+                format!("Expected {} for '{}', found {}", declared, name, found)
+            }
+            ErrorType::UnknownType(name) => format!("Unknown type '{}'", name),
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "at line {}, column {}: {}",
+            self.span.start().line(),
+            self.span.start().column(),
+            self.error_type
+        )
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,825 @@
+mod error;
+
+pub use error::*;
+
+use std::{collections::HashMap, fmt, mem, rc::Rc};
+
+use crate::{
+    interpreter::builtins::{self, Kind},
+    parser::{
+        self, Annotation, Expr, ExprType, Identifier, Parameter, RecordDefinition, Stmt, StmtType,
+        Subroutine,
+    },
+    scanner::TokenType,
+    Span, Value,
+};
+
+// Loops are re-checked until their variable types settle; joining always
+// widens towards `Unknown`, so this bound is only a safeguard
+const MAX_PASSES: usize = 16;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Int,
+    Float,
+    Bool,
+    String,
+    Char,
+    Array(Box<Type>),
+    Record(String),
+    // The type depends on how the program runs, so anything is allowed
+    Unknown,
+}
+
+const SCALARS: [Type; 5] = [Type::Int, Type::Float, Type::Bool, Type::String, Type::Char];
+
+impl Type {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Int(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::Bool(_) => Type::Bool,
+            Value::String(_) => Type::String,
+            Value::Char(_) => Type::Char,
+            Value::Array(_) | Value::Record(_) => Type::Unknown,
+        }
+    }
+
+    fn kind(kind: &Kind) -> Self {
+        match kind {
+            Kind::Integer => Type::Int,
+            Kind::Real => Type::Float,
+            Kind::Boolean => Type::Bool,
+            Kind::String => Type::String,
+            Kind::Char => Type::Char,
+            Kind::Array => Type::Array(Box::new(Type::Unknown)),
+        }
+    }
+
+    // Values are converted to their declared type when they are stored
+    fn declared(annotation: &Annotation) -> Self {
+        match annotation {
+            Annotation::Integer => Type::Int,
+            Annotation::Real => Type::Float,
+            Annotation::Boolean => Type::Bool,
+            Annotation::String => Type::String,
+            Annotation::Char => Type::Char,
+            Annotation::Array(element) => Type::Array(Box::new(Type::declared(element))),
+            Annotation::Record(name) => Type::Record(name.clone()),
+        }
+    }
+
+    fn join(self, other: Type) -> Self {
+        match (self, other) {
+            (Type::Array(left), Type::Array(right)) => Type::Array(Box::new(left.join(*right))),
+            (left, right) if left == right => left,
+            _ => Type::Unknown,
+        }
+    }
+
+    fn is_known(&self) -> bool {
+        *self != Type::Unknown
+    }
+
+    // The concrete types a value of this type could have at runtime, as far
+    // as operators are concerned
+    fn candidates(&self) -> &[Type] {
+        match self {
+            Type::Unknown => &SCALARS,
+            known => std::slice::from_ref(known),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "Integer"),
+            Type::Float => write!(f, "Real"),
+            Type::Bool => write!(f, "Boolean"),
+            Type::String => write!(f, "String"),
+            Type::Char => write!(f, "Char"),
+            Type::Array(element) if element.is_known() => write!(f, "Array of {}", element),
+            Type::Array(_) => write!(f, "Array"),
+            Type::Record(name) => write!(f, "{}", name),
+            Type::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+struct Signature {
+    parameters: Vec<Parameter>,
+    returns: Type,
+}
+
+struct Checker {
+    // `None` when the name is declared more than once, so which one is called
+    // depends on the order the program runs in
+    subroutines: HashMap<String, Option<Signature>>,
+    records: HashMap<String, Option<Rc<RecordDefinition>>>,
+    variables: HashMap<String, Type>,
+    // Variables declared with a type on every path, which assignments must
+    // conform to
+    declared: HashMap<String, Annotation>,
+    in_subroutine: bool,
+    returns: Option<Type>,
+    // Arrays are shared, so storing a differently typed element through one
+    // variable changes the arrays seen through others
+    trust_elements: bool,
+    mixed_arrays: bool,
+    reporting: bool,
+    errors: Vec<Error>,
+}
+
+/// Infers the types of variables and subroutine results in `program` and
+/// reports every operation that would fail with them. Anything whose type
+/// depends on how the program runs is assumed to be valid.
+pub fn check(program: &[Stmt]) -> Vec<Error> {
+    let mut checker = Checker::new(program, true);
+    checker.program(program);
+
+    if checker.mixed_arrays {
+        checker = Checker::new(program, false);
+        checker.program(program);
+    }
+
+    let mut errors = checker.errors;
+    for (type_name, name) in parser::unknown_types(program, |_| false) {
+        errors.push(Error::new(ErrorType::UnknownType(type_name), name.span));
+    }
+    errors.sort_by_key(|error| error.span.start().offset());
+    errors
+}
+
+impl Checker {
+    fn new(program: &[Stmt], trust_elements: bool) -> Self {
+        let mut subroutines = HashMap::new();
+        let mut records = HashMap::new();
+        for stmt in statements(program) {
+            match &stmt.stmt_type {
+                StmtType::Subroutine(subroutine) => {
+                    let signature = Signature {
+                        parameters: subroutine.parameters.clone(),
+                        returns: subroutine
+                            .returns
+                            .as_ref()
+                            .map_or(Type::Unknown, Type::declared),
+                    };
+                    subroutines
+                        .entry(subroutine.name.name.clone())
+                        .and_modify(|signature| *signature = None)
+                        .or_insert(Some(signature));
+                }
+                StmtType::Record(record) => {
+                    records
+                        .entry(record.name.name.clone())
+                        .and_modify(|record| *record = None)
+                        .or_insert(Some(record.clone()));
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            subroutines,
+            records,
+            variables: HashMap::new(),
+            declared: HashMap::new(),
+            in_subroutine: false,
+            returns: None,
+            trust_elements,
+            mixed_arrays: false,
+            reporting: false,
+            errors: Vec::new(),
+        }
+    }
+
+    fn program(&mut self, program: &[Stmt]) {
+        let subroutines: Vec<&Rc<Subroutine>> = statements(program)
+            .into_iter()
+            .filter_map(|stmt| match &stmt.stmt_type {
+                StmtType::Subroutine(subroutine) => Some(subroutine),
+                _ => None,
+            })
+            .collect();
+
+        // Each pass can only learn the result of subroutines whose callees
+        // were settled by the one before
+        for _ in 0..=subroutines.len() {
+            let mut changed = false;
+            for subroutine in &subroutines {
+                let returns = self.subroutine(subroutine);
+                if subroutine.returns.is_some() {
+                    continue;
+                }
+                if let Some(Some(signature)) = self.subroutines.get_mut(&subroutine.name.name) {
+                    changed |= signature.returns != returns;
+                    signature.returns = returns;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        self.reporting = true;
+        for subroutine in &subroutines {
+            self.subroutine(subroutine);
+        }
+        self.block(program);
+    }
+
+    fn subroutine(&mut self, subroutine: &Subroutine) -> Type {
+        let parameters = subroutine
+            .parameters
+            .iter()
+            .map(|parameter| {
+                let declared = parameter
+                    .annotation
+                    .as_ref()
+                    .map_or(Type::Unknown, Type::declared);
+                (parameter.name.name.clone(), declared)
+            })
+            .collect();
+        let declared = subroutine
+            .parameters
+            .iter()
+            .filter_map(|parameter| {
+                let annotation = parameter.annotation.clone()?;
+                Some((parameter.name.name.clone(), annotation))
+            })
+            .collect();
+        let globals = mem::replace(&mut self.variables, parameters);
+        let global_declared = mem::replace(&mut self.declared, declared);
+        self.in_subroutine = true;
+
+        self.block(&subroutine.body);
+
+        self.in_subroutine = false;
+        self.variables = globals;
+        self.declared = global_declared;
+        self.returns.take().unwrap_or(Type::Unknown)
+    }
+
+    fn error(&mut self, error_type: ErrorType, span: Span) {
+        if self.reporting {
+            self.errors.push(Error::new(error_type, span));
+        }
+    }
+
+    // Combines the variables of two paths through the program
+    fn merge(&mut self, mut other: HashMap<String, Type>, declared: HashMap<String, Annotation>) {
+        self.declared
+            .retain(|name, annotation| declared.get(name) == Some(annotation));
+
+        // Inside a subroutine, a path that never assigned a name reads the
+        // global instead
+        for (name, current) in self.variables.iter_mut() {
+            *current = match other.remove(name) {
+                Some(other_type) => mem::replace(current, Type::Unknown).join(other_type),
+                None if self.in_subroutine => Type::Unknown,
+                None => continue,
+            };
+        }
+        for (name, other_type) in other {
+            let merged = if self.in_subroutine {
+                Type::Unknown
+            } else {
+                other_type
+            };
+            self.variables.insert(name, merged);
+        }
+    }
+
+    fn repeatedly(&mut self, mut pass: impl FnMut(&mut Self)) {
+        let reporting = mem::replace(&mut self.reporting, false);
+        for _ in 0..MAX_PASSES {
+            let before = self.variables.clone();
+            let declared = self.declared.clone();
+            pass(self);
+            self.merge(before.clone(), declared.clone());
+            if self.variables == before && self.declared == declared {
+                break;
+            }
+        }
+
+        self.reporting = reporting;
+        let before = self.variables.clone();
+        let declared = self.declared.clone();
+        pass(self);
+        self.merge(before, declared);
+    }
+
+    fn block(&mut self, block: &[Stmt]) {
+        for stmt in block {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match &stmt.stmt_type {
+            StmtType::Expression(expr) => {
+                self.expression(expr);
+            }
+            StmtType::Assign(name, expr) | StmtType::Constant(name, None, expr) => {
+                let value = self.expression(expr);
+                let value = match self.declared.get(&name.name).cloned() {
+                    Some(annotation) => self.conform(expr, value, &annotation, name),
+                    None => value,
+                };
+                self.variables.insert(name.name.clone(), value);
+            }
+            StmtType::Declare(name, annotation, expr) => {
+                if let Some(expr) = expr {
+                    let value = self.expression(expr);
+                    self.conform(expr, value, annotation, name);
+                }
+                self.declare(name, annotation);
+            }
+            StmtType::Constant(name, Some(annotation), expr) => {
+                let value = self.expression(expr);
+                self.conform(expr, value, annotation, name);
+                self.declare(name, annotation);
+            }
+            StmtType::AssignIndex(array, index, expr) => {
+                let (array_type, place) = self.place(array);
+                self.expect_int(index);
+                let mut value = self.expression(expr);
+
+                if let Some((name, Annotation::Array(element))) = place {
+                    let name = Identifier {
+                        name,
+                        span: array.span,
+                    };
+                    value = self.conform(expr, value, &element, &name);
+                }
+                match array_type {
+                    Type::Array(element) if value.is_known() && *element == value => {}
+                    Type::Array(_) | Type::Unknown => self.mixed_arrays = true,
+                    other => self.error(ErrorType::NotIndexable(other.to_string()), array.span),
+                }
+            }
+            StmtType::AssignField(record, field, expr) => {
+                let record_type = self.expression(record);
+                let annotation = self.field_annotation(&record_type, field);
+                self.field(record_type, record.span, field);
+                let value = self.expression(expr);
+
+                if let Some(annotation) = annotation {
+                    self.conform(expr, value, &annotation, field);
+                }
+            }
+            StmtType::Output(exprs) => {
+                for expr in exprs {
+                    self.expression(expr);
+                }
+            }
+            StmtType::If(condition, then_branch, else_branch) => {
+                self.condition(condition);
+
+                let before = self.variables.clone();
+                let declared = self.declared.clone();
+                self.block(then_branch);
+                let then_variables = mem::replace(&mut self.variables, before);
+                let then_declared = mem::replace(&mut self.declared, declared);
+                self.block(else_branch);
+                self.merge(then_variables, then_declared);
+            }
+            StmtType::For(name, start, end, step, body) => {
+                self.expect_int(start);
+                self.expect_int(end);
+                if let Some(step) = step {
+                    self.expect_int(step);
+                }
+
+                self.repeatedly(|checker| {
+                    checker.variables.insert(name.name.clone(), Type::Int);
+                    checker.block(body);
+                });
+            }
+            StmtType::ForIn(name, iterable, body) => {
+                let item = match self.expression(iterable) {
+                    Type::Array(element) if self.trust_elements => *element,
+                    Type::Array(_) | Type::Unknown => Type::Unknown,
+                    Type::String => Type::Char,
+                    other => {
+                        self.error(ErrorType::NotIterable(other.to_string()), iterable.span);
+                        Type::Unknown
+                    }
+                };
+
+                self.repeatedly(|checker| {
+                    checker.variables.insert(name.name.clone(), item.clone());
+                    checker.block(body);
+                });
+            }
+            StmtType::While(condition, body) => self.repeatedly(|checker| {
+                checker.condition(condition);
+                checker.block(body);
+            }),
+            StmtType::Repeat(body, condition) => self.repeatedly(|checker| {
+                checker.block(body);
+                checker.condition(condition);
+            }),
+            // Bodies are checked once up front, wherever they are declared (see `statements`)
+            StmtType::Subroutine(_) | StmtType::Record(_) => {}
+            StmtType::Return(expr) => {
+                if let Some(expr) = expr {
+                    let value = self.expression(expr);
+                    self.returns = Some(match self.returns.take() {
+                        Some(returns) => returns.join(value),
+                        None => value,
+                    });
+                }
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Identifier, annotation: &Annotation) {
+        self.variables
+            .insert(name.name.clone(), Type::declared(annotation));
+        self.declared.insert(name.name.clone(), annotation.clone());
+    }
+
+    // Reports a value that can never have the declared type, and returns the
+    // type it is converted to when it is stored
+    fn conform(
+        &mut self,
+        expr: &Expr,
+        value: Type,
+        annotation: &Annotation,
+        name: &Identifier,
+    ) -> Type {
+        let declared = Type::declared(annotation);
+        // A type that does not exist is reported on its own
+        let exists = annotation
+            .record()
+            .is_none_or(|record| self.records.contains_key(record));
+        if exists && !conforms(&value, &declared) {
+            self.error(
+                ErrorType::DeclaredType(
+                    name.name.clone(),
+                    annotation.to_string(),
+                    value.to_string(),
+                ),
+                name.span,
+            );
+            return declared;
+        }
+
+        // Storing an array under a declared type converts its elements in
+        // place, which changes the elements seen through other variables
+        if matches!(value, Type::Array(_)) && value != declared && !unshared(expr) {
+            self.mixed_arrays = true;
+        }
+        declared
+    }
+
+    fn expect_int(&mut self, expr: &Expr) {
+        let found = self.expression(expr);
+        if found.is_known() && found != Type::Int {
+            self.error(ErrorType::ExpectedInt(found.to_string()), expr.span);
+        }
+    }
+
+    fn condition(&mut self, expr: &Expr) {
+        let found = self.expression(expr);
+        if found.is_known() && found != Type::Bool {
+            self.error(ErrorType::NonBoolCondition(found.to_string()), expr.span);
+        }
+    }
+
+    // The type declared for a field, when the record's type is known
+    fn field_annotation(&self, record_type: &Type, field: &Identifier) -> Option<Annotation> {
+        let Type::Record(name) = record_type else {
+            return None;
+        };
+        let Some(Some(record)) = self.records.get(name) else {
+            return None;
+        };
+        record
+            .fields
+            .iter()
+            .find(|record_field| record_field.name.name == field.name)
+            .map(|record_field| record_field.annotation.clone())
+    }
+
+    // Checks the array of an element store, and finds the name and type it
+    // was declared with by the variable or field it was read from
+    fn place(&mut self, expr: &Expr) -> (Type, Option<(String, Annotation)>) {
+        match &expr.expr_type {
+            ExprType::Variable(name) => {
+                let place = self
+                    .declared
+                    .get(&name.name)
+                    .map(|annotation| (name.name.clone(), annotation.clone()));
+                (self.expression(expr), place)
+            }
+            ExprType::Field(record, field) => {
+                let record_type = self.expression(record);
+                let place = self
+                    .field_annotation(&record_type, field)
+                    .map(|annotation| (field.name.clone(), annotation));
+                (self.field(record_type, record.span, field), place)
+            }
+            ExprType::Index(array, index) => {
+                let (array_type, place) = self.place(array);
+                let element = self.index(array_type, array, index);
+                let place = match place {
+                    Some((name, Annotation::Array(element))) => Some((name, *element)),
+                    _ => None,
+                };
+                (element, place)
+            }
+            _ => (self.expression(expr), None),
+        }
+    }
+
+    // Returns the type of the element
+    fn index(&mut self, array_type: Type, array: &Expr, index: &Expr) -> Type {
+        self.expect_int(index);
+
+        match array_type {
+            Type::Array(element) if self.trust_elements => *element,
+            Type::Array(_) | Type::Unknown => Type::Unknown,
+            other => {
+                self.error(ErrorType::NotIndexable(other.to_string()), array.span);
+                Type::Unknown
+            }
+        }
+    }
+
+    // Returns the declared type of the field
+    fn field(&mut self, record_type: Type, span: Span, field: &Identifier) -> Type {
+        match record_type {
+            Type::Record(name) => {
+                let Some(Some(record)) = self.records.get(&name) else {
+                    return Type::Unknown;
+                };
+                match record
+                    .fields
+                    .iter()
+                    .find(|record_field| record_field.name.name == field.name)
+                {
+                    Some(record_field) => Type::declared(&record_field.annotation),
+                    None => {
+                        self.error(
+                            ErrorType::UnknownField(name, field.name.clone()),
+                            field.span,
+                        );
+                        Type::Unknown
+                    }
+                }
+            }
+            Type::Unknown => Type::Unknown,
+            other => {
+                self.error(ErrorType::NotARecord(other.to_string()), span);
+                Type::Unknown
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr) -> Type {
+        match &expr.expr_type {
+            ExprType::Literal(value) => Type::of(value),
+            ExprType::Variable(name) => self
+                .variables
+                .get(&name.name)
+                .cloned()
+                .unwrap_or(Type::Unknown),
+            ExprType::Call(name, arguments) => self.call(name, arguments),
+            ExprType::UserInput => Type::String,
+            ExprType::Array(elements) => {
+                let mut element = None;
+                for expr in elements {
+                    let value = self.expression(expr);
+                    element = Some(match element {
+                        Some(element) => value.join(element),
+                        None => value,
+                    });
+                }
+                Type::Array(Box::new(element.unwrap_or(Type::Unknown)))
+            }
+            ExprType::Index(array, index) => {
+                let array_type = self.expression(array);
+                self.index(array_type, array, index)
+            }
+            ExprType::Field(record, field) => {
+                let record_type = self.expression(record);
+                self.field(record_type, record.span, field)
+            }
+            ExprType::Unary(operator, right) => {
+                let right_type = self.expression(right);
+                let result = match (&operator.token_type, &right_type) {
+                    (_, Type::Unknown) if operator.token_type == TokenType::LogicalNot => {
+                        Some(Type::Bool)
+                    }
+                    (_, Type::Unknown) => Some(Type::Unknown),
+                    (TokenType::Subtract, Type::Int | Type::Float) => Some(right_type.clone()),
+                    (TokenType::LogicalNot, Type::Bool) => Some(Type::Bool),
+                    _ => None,
+                };
+
+                result.unwrap_or_else(|| {
+                    self.error(
+                        ErrorType::MismatchedOperand(
+                            operator.token_type.describe(),
+                            right_type.to_string(),
+                        ),
+                        expr.span,
+                    );
+                    Type::Unknown
+                })
+            }
+            ExprType::Binary(left, operator, right) => {
+                let left_type = self.expression(left);
+                let right_type = self.expression(right);
+
+                // Every combination of the runtime types the operands could have
+                let mut result: Option<Type> = None;
+                for left_candidate in left_type.candidates() {
+                    for right_candidate in right_type.candidates() {
+                        if let Some(value) =
+                            binary(left_candidate, &operator.token_type, right_candidate)
+                        {
+                            result = Some(match result {
+                                Some(result) => result.join(value),
+                                None => value,
+                            });
+                        }
+                    }
+                }
+
+                result.unwrap_or_else(|| {
+                    self.error(
+                        ErrorType::MismatchedOperands(
+                            operator.token_type.describe(),
+                            left_type.to_string(),
+                            right_type.to_string(),
+                        ),
+                        expr.span,
+                    );
+                    Type::Unknown
+                })
+            }
+            ExprType::Logical(left, _, right) => {
+                for operand in [left, right] {
+                    let found = self.expression(operand);
+                    if found.is_known() && found != Type::Bool {
+                        self.error(ErrorType::ExpectedBool(found.to_string()), operand.span);
+                    }
+                }
+                Type::Bool
+            }
+        }
+    }
+
+    fn call(&mut self, name: &Identifier, arguments: &[Expr]) -> Type {
+        let argument_types: Vec<Type> = arguments
+            .iter()
+            .map(|argument| self.expression(argument))
+            .collect();
+
+        // Subroutines shadow records, which shadow builtins
+        if let Some(signature) = self.subroutines.get(&name.name) {
+            let Some(signature) = signature else {
+                return Type::Unknown;
+            };
+            let (parameters, returns) = (signature.parameters.clone(), signature.returns.clone());
+            if self.arity(name, parameters.len(), argument_types.len()) {
+                for ((parameter, argument), value) in
+                    parameters.iter().zip(arguments).zip(argument_types)
+                {
+                    if let Some(annotation) = &parameter.annotation {
+                        self.argument(argument, value, annotation, &parameter.name);
+                    }
+                }
+            }
+            return returns;
+        }
+
+        if let Some(record) = self.records.get(&name.name) {
+            if let Some(record) = record.clone() {
+                if self.arity(name, record.fields.len(), argument_types.len()) {
+                    for ((field, argument), value) in
+                        record.fields.iter().zip(arguments).zip(argument_types)
+                    {
+                        self.argument(argument, value, &field.annotation, &field.name);
+                    }
+                }
+            }
+            return Type::Record(name.name.clone());
+        }
+
+        match builtin(&name.name) {
+            Some((parameters, returns)) => {
+                if self.arity(name, parameters.len(), argument_types.len()) {
+                    for (accepted, argument) in parameters.iter().zip(&argument_types) {
+                        let matches = accepted.iter().any(|accepted| match accepted {
+                            Type::Array(_) => matches!(argument, Type::Array(_)),
+                            accepted => accepted == argument,
+                        });
+                        if argument.is_known() && !matches {
+                            self.error(
+                                ErrorType::MismatchedArgument(
+                                    name.name.clone(),
+                                    argument.to_string(),
+                                ),
+                                name.span,
+                            );
+                        }
+                    }
+                }
+                returns
+            }
+            None => Type::Unknown,
+        }
+    }
+
+    // Arguments are reported where they are written, as the interpreter does
+    fn argument(
+        &mut self,
+        argument: &Expr,
+        value: Type,
+        annotation: &Annotation,
+        name: &Identifier,
+    ) {
+        let name = Identifier {
+            name: name.name.clone(),
+            span: argument.span,
+        };
+        self.conform(argument, value, annotation, &name);
+    }
+
+    fn arity(&mut self, name: &Identifier, expected: usize, found: usize) -> bool {
+        if expected != found {
+            self.error(
+                ErrorType::ArityMismatch(name.name.clone(), expected, found),
+                name.span,
+            );
+        }
+        expected == found
+    }
+}
+
+// Every statement in `block`, including those nested in other statements,
+// so subroutines and records are found wherever they are declared
+fn statements(block: &[Stmt]) -> Vec<&Stmt> {
+    let mut found = Vec::new();
+    for stmt in block {
+        found.push(stmt);
+        match &stmt.stmt_type {
+            StmtType::If(_, then_branch, else_branch) => {
+                found.extend(statements(then_branch));
+                found.extend(statements(else_branch));
+            }
+            StmtType::For(.., body)
+            | StmtType::ForIn(.., body)
+            | StmtType::While(_, body)
+            | StmtType::Repeat(body, _) => found.extend(statements(body)),
+            StmtType::Subroutine(subroutine) => found.extend(statements(&subroutine.body)),
+            _ => {}
+        }
+    }
+    found
+}
+
+// Whether a value of type `found` can be stored under the `declared` type,
+// allowing for the conversions made when it is stored
+fn conforms(found: &Type, declared: &Type) -> bool {
+    match (declared, found) {
+        (_, Type::Unknown) => true,
+        // A one-character String becomes a Char, which only shows when it runs
+        (Type::Float, Type::Int) | (Type::Char, Type::String) => true,
+        (Type::Array(declared), Type::Array(found)) => conforms(found, declared),
+        (declared, found) => declared == found,
+    }
+}
+
+// Whether `expr` builds a value that nothing else can refer to yet
+fn unshared(expr: &Expr) -> bool {
+    match &expr.expr_type {
+        ExprType::Array(elements) => elements.iter().all(unshared),
+        ExprType::Variable(_) | ExprType::Index(..) | ExprType::Field(..) | ExprType::Call(..) => {
+            false
+        }
+        _ => true,
+    }
+}
+
+// Mirrors the arms generated by build.rs for `interpreter::binary`
+fn binary(left: &Type, operator: &TokenType, right: &Type) -> Option<Type> {
+    include!(concat!(env!("OUT_DIR"), "/binary_types.rs"))
+}
+
+// The parameter types each builtin accepts, and the type it returns
+fn builtin(name: &str) -> Option<(Vec<Vec<Type>>, Type)> {
+    let builtin = builtins::lookup(name)?;
+    let parameters = builtin
+        .parameters
+        .iter()
+        .map(|accepted| accepted.iter().map(Type::kind).collect())
+        .collect();
+
+    Some((parameters, Type::kind(&builtin.returns)))
+}
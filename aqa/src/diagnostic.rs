@@ -0,0 +1,272 @@
+use crate::{checker, interpreter, parser, scanner, Error, Span};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorMode {
+    Plain,
+    Ansi,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+const UNKNOWN_TYPE_HELP: &str =
+    "use Integer, Real, Boolean, String or Char, or declare the type with RECORD";
+
+impl Diagnostic {
+    pub fn new(message: String, span: Span) -> Self {
+        Self {
+            message,
+            span,
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    pub fn render(&self, path: &str, source: &str, mode: ColorMode) -> String {
+        let paint = |style: &str, text: &str| match mode {
+            ColorMode::Plain => text.to_string(),
+            ColorMode::Ansi => format!("{}{}{}", style, text, RESET),
+        };
+
+        let start = self.span.start();
+        let line_number = start.line().to_string();
+        let gutter = " ".repeat(line_number.len());
+        let bar = paint(BLUE, "|");
+
+        let mut rendered = format!("{}: {}\n", paint(RED, "error"), paint(BOLD, &self.message));
+        rendered += &format!(
+            "{}{} {}:{}:{}\n",
+            gutter,
+            paint(BLUE, "-->"),
+            path,
+            start.line(),
+            start.column()
+        );
+
+        // A span from another source has no line here to show
+        if start.offset() <= source.len() {
+            let offset = floor_char_boundary(source, start.offset());
+            let line_start = source[..offset].rfind('\n').map_or(0, |index| index + 1);
+            let line_end = source[offset..]
+                .find('\n')
+                .map_or(source.len(), |index| offset + index);
+            let line = source[line_start..line_end].trim_end_matches('\r');
+
+            // Only the first line of a multi-line span is underlined. A span
+            // starting on a trimmed '\r' still gets its one caret
+            let underline_end = (line_start + line.len()).max(offset);
+            let end = floor_char_boundary(
+                source,
+                self.span.end().offset().clamp(offset, underline_end),
+            );
+            let padding: String = source[line_start..offset]
+                .chars()
+                .map(|char| if char == '\t' { '\t' } else { ' ' })
+                .collect();
+            let carets = "^".repeat(source[offset..end].chars().count().max(1));
+
+            rendered += &format!("{} {}\n", gutter, bar);
+            rendered += &format!("{} {} {}\n", paint(BLUE, &line_number), bar, line);
+            rendered += &format!("{} {} {}{}\n", gutter, bar, padding, paint(RED, &carets));
+        }
+
+        for note in &self.notes {
+            rendered += &format!(
+                "{} {} {}: {}\n",
+                gutter,
+                paint(BLUE, "="),
+                paint(BOLD, "note"),
+                note
+            );
+        }
+        if let Some(help) = &self.help {
+            rendered += &format!(
+                "{} {} {}: {}\n",
+                gutter,
+                paint(BLUE, "="),
+                paint(CYAN, "help"),
+                help
+            );
+        }
+
+        rendered
+    }
+}
+
+fn floor_char_boundary(source: &str, mut offset: usize) -> usize {
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+fn keyword(token_type: &scanner::TokenType) -> String {
+    match token_type.keyword() {
+        Some(keyword) => keyword.to_string(),
+        None => token_type.describe(),
+    }
+}
+
+impl From<&scanner::Error> for Diagnostic {
+    fn from(error: &scanner::Error) -> Self {
+        let diagnostic = Diagnostic::new(error.error_type.to_string(), error.span);
+
+        match &error.error_type {
+            scanner::ErrorType::InvalidCharLiteral(_) => diagnostic
+                .with_note(String::from(
+                    "double quotes make a Char, which holds exactly one character",
+                ))
+                .with_help(String::from(
+                    "strings are written with single quotes, e.g. 'hello'",
+                )),
+            scanner::ErrorType::UnexpectedEOF => diagnostic
+                .with_note(String::from(
+                    "the file ended before this token was finished",
+                ))
+                .with_help(String::from("check for a missing closing quote")),
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<&parser::Error> for Diagnostic {
+    fn from(error: &parser::Error) -> Self {
+        let diagnostic = Diagnostic::new(error.error_type.to_string(), error.span);
+
+        match &error.error_type {
+            parser::ErrorType::MismatchedTerminator(opener, expected, _) => diagnostic
+                .with_note(format!("the innermost open block is {}", keyword(opener)))
+                .with_help(format!("did you mean {}?", keyword(expected))),
+            parser::ErrorType::UnterminatedBlock(opener, terminator) => {
+                diagnostic.with_help(format!(
+                    "add {} after the last statement of this {} block",
+                    keyword(terminator),
+                    keyword(opener)
+                ))
+            }
+            parser::ErrorType::UnexpectedToken(
+                found @ (scanner::TokenType::Else
+                | scanner::TokenType::EndIf
+                | scanner::TokenType::EndFor
+                | scanner::TokenType::EndWhile
+                | scanner::TokenType::Until
+                | scanner::TokenType::EndSubroutine
+                | scanner::TokenType::EndRecord),
+            ) => diagnostic.with_note(format!(
+                "there is no open block for {} to close",
+                keyword(found)
+            )),
+            parser::ErrorType::Expected(expected, _) if expected.keyword().is_some() => {
+                diagnostic.with_help(format!("add {} here", keyword(expected)))
+            }
+            parser::ErrorType::ExpectedLineBreak(_) => {
+                diagnostic.with_help(String::from("each statement must start on a new line"))
+            }
+            parser::ErrorType::InvalidAssignmentTarget => diagnostic.with_note(String::from(
+                "only variables, array elements and record fields can be assigned to",
+            )),
+            parser::ErrorType::NestedSubroutine => diagnostic.with_help(String::from(
+                "move this subroutine after the ENDSUBROUTINE of the one enclosing it",
+            )),
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<&checker::Error> for Diagnostic {
+    fn from(error: &checker::Error) -> Self {
+        let diagnostic = Diagnostic::new(error.error_type.to_string(), error.span);
+
+        match &error.error_type {
+            checker::ErrorType::MismatchedOperands(operator, left, right)
+                if operator == "'+'" && (left == "String" || right == "String") =>
+            {
+                let other = if left == "String" { right } else { left };
+                match other.as_str() {
+                    "Integer" => diagnostic.with_help(String::from(
+                        "convert the Integer with INT_TO_STRING before joining it to a String",
+                    )),
+                    "Real" => diagnostic.with_help(String::from(
+                        "convert the Real with REAL_TO_STRING before joining it to a String",
+                    )),
+                    _ => diagnostic,
+                }
+            }
+            checker::ErrorType::NonBoolCondition(_) => diagnostic.with_help(String::from(
+                "use a comparison such as 'x > 0' to produce a Boolean",
+            )),
+            checker::ErrorType::UnknownType(_) => {
+                diagnostic.with_help(String::from(UNKNOWN_TYPE_HELP))
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<&interpreter::Error> for Diagnostic {
+    fn from(error: &interpreter::Error) -> Self {
+        let diagnostic = Diagnostic::new(error.error_type.to_string(), error.span);
+
+        match &error.error_type {
+            interpreter::ErrorType::UndefinedVariable(name) => diagnostic.with_help(format!(
+                "assign a value with '{} <- ...' before using it",
+                name
+            )),
+            interpreter::ErrorType::ConstantReassignment(_) => diagnostic.with_note(String::from(
+                "constants cannot change once they are declared",
+            )),
+            interpreter::ErrorType::NonBoolCondition(_) => diagnostic.with_help(String::from(
+                "use a comparison such as 'x > 0' to produce a Boolean",
+            )),
+            interpreter::ErrorType::StackOverflow(_) => diagnostic.with_note(String::from(
+                "the subroutine may be calling itself without reaching a base case",
+            )),
+            interpreter::ErrorType::UnknownType(_) => {
+                diagnostic.with_help(String::from(UNKNOWN_TYPE_HELP))
+            }
+            interpreter::ErrorType::IntegerOverflow => diagnostic.with_note(format!(
+                "Integers must lie between {} and {}",
+                i64::MIN,
+                i64::MAX
+            )),
+            interpreter::ErrorType::IndexOutOfBounds(_, length) if *length > 0 => diagnostic
+                .with_note(format!(
+                    "arrays are indexed from 0, so the last valid index is {}",
+                    length - 1
+                )),
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<&Error> for Diagnostic {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::ScannerError(error) => error.into(),
+            Error::ParserError(error) => error.into(),
+            Error::CheckerError(error) => error.into(),
+            Error::InterpreterError(error) => error.into(),
+        }
+    }
+}
@@ -1,19 +1,32 @@
 use core::fmt;
 
-use crate::{interpreter, parser, scanner};
+use crate::{checker, interpreter, parser, scanner, Span};
 
 #[derive(Debug)]
 pub enum Error {
     ScannerError(scanner::Error),
     ParserError(parser::Error),
+    CheckerError(checker::Error),
     InterpreterError(interpreter::Error),
 }
 
+impl Error {
+    pub fn span(&self) -> Span {
+        match self {
+            Error::ScannerError(error) => error.span,
+            Error::ParserError(error) => error.span,
+            Error::CheckerError(error) => error.span,
+            Error::InterpreterError(error) => error.span,
+        }
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::ScannerError(error) => error.fmt(f),
             Error::ParserError(error) => error.fmt(f),
+            Error::CheckerError(error) => error.fmt(f),
             Error::InterpreterError(error) => error.fmt(f),
         }
     }
@@ -31,6 +44,12 @@ impl From<parser::Error> for Error {
     }
 }
 
+impl From<checker::Error> for Error {
+    fn from(value: checker::Error) -> Self {
+        Self::CheckerError(value)
+    }
+}
+
 impl From<interpreter::Error> for Error {
     fn from(value: interpreter::Error) -> Self {
         Self::InterpreterError(value)
@@ -0,0 +1,102 @@
+use crate::{parser::Annotation, Span, Value};
+
+use super::{conform, Error, ErrorType, Result};
+
+/// What a name is bound to, shared by the tree walker's scopes and the VM's
+/// slots so both apply the same rules for constants and declared types.
+#[derive(Debug, Clone)]
+pub struct Binding {
+    // A declared variable has no value until it is first assigned
+    value: Option<Value>,
+    constant: bool,
+    annotation: Option<Annotation>,
+}
+
+impl Binding {
+    pub fn new(value: Value, constant: bool) -> Self {
+        Self {
+            value: Some(value),
+            constant,
+            annotation: None,
+        }
+    }
+
+    // Parameters are checked against their annotation by the caller
+    pub fn parameter(value: Value, annotation: Option<Annotation>) -> Self {
+        Self {
+            value: Some(value),
+            constant: false,
+            annotation,
+        }
+    }
+
+    pub fn declare(
+        name: &str,
+        annotation: &Annotation,
+        value: Option<Value>,
+        constant: bool,
+        span: Span,
+    ) -> Result<Self> {
+        let value = match value {
+            Some(value) => Some(conform(value, annotation, name, span)?),
+            None => None,
+        };
+
+        Ok(Self {
+            value,
+            constant,
+            annotation: Some(annotation.clone()),
+        })
+    }
+
+    pub fn value(&self) -> Option<&Value> {
+        self.value.as_ref()
+    }
+
+    pub fn annotation(&self) -> Option<&Annotation> {
+        self.annotation.as_ref()
+    }
+
+    /// Replaces the value, which must have the declared type if there is one.
+    pub fn assign(&mut self, name: &str, value: Value, constant: bool, span: Span) -> Result<()> {
+        let value = match &self.annotation {
+            Some(annotation) => conform(value, annotation, name, span)?,
+            None => value,
+        };
+        self.value = Some(value);
+        self.constant = constant;
+        Ok(())
+    }
+}
+
+/// Reads the value a name is bound to, or reports it as undefined.
+pub fn lookup(binding: Option<&Binding>, name: &str, span: Span) -> Result<Value> {
+    match binding.and_then(Binding::value) {
+        Some(value) => Ok(value.clone()),
+        None => Err(Error::new(
+            ErrorType::UndefinedVariable(name.to_string()),
+            span,
+        )),
+    }
+}
+
+/// Rejects rebinding a name that is a constant in either scope it could
+/// resolve to.
+pub fn check_constant(
+    local: Option<&Binding>,
+    global: Option<&Binding>,
+    name: &str,
+    span: Span,
+) -> Result<()> {
+    if [local, global]
+        .into_iter()
+        .flatten()
+        .any(|binding| binding.constant)
+    {
+        return Err(Error::new(
+            ErrorType::ConstantReassignment(name.to_string()),
+            span,
+        ));
+    }
+    Ok(())
+}
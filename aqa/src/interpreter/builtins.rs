@@ -0,0 +1,292 @@
+use crate::{parser::Identifier, Value};
+
+use super::{random::Rng, Error, ErrorType, Result};
+
+/// The kinds of value builtins accept and return. The type checker reads
+/// them from the same table, so its signatures cannot drift from these.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Integer,
+    Real,
+    Boolean,
+    String,
+    Char,
+    Array,
+}
+
+impl Kind {
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Int(_) => Some(Kind::Integer),
+            Value::Float(_) => Some(Kind::Real),
+            Value::Bool(_) => Some(Kind::Boolean),
+            Value::String(_) => Some(Kind::String),
+            Value::Char(_) => Some(Kind::Char),
+            Value::Array(_) => Some(Kind::Array),
+            Value::Record(_) => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Builtin {
+    pub name: &'static str,
+    // The kinds each parameter accepts; a String is listed where a
+    // one-character String is accepted as a Char
+    pub parameters: &'static [&'static [Kind]],
+    pub returns: Kind,
+    pub function: fn(&mut Rng, Vec<Value>, &Identifier) -> Result<Value>,
+}
+
+const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "LEN",
+        parameters: &[&[Kind::Array, Kind::String]],
+        returns: Kind::Integer,
+        function: len,
+    },
+    Builtin {
+        name: "POSITION",
+        parameters: &[&[Kind::String], &[Kind::Char, Kind::String]],
+        returns: Kind::Integer,
+        function: position,
+    },
+    Builtin {
+        name: "SUBSTRING",
+        parameters: &[&[Kind::Integer], &[Kind::Integer], &[Kind::String]],
+        returns: Kind::String,
+        function: substring,
+    },
+    Builtin {
+        name: "CHAR_TO_CODE",
+        parameters: &[&[Kind::Char, Kind::String]],
+        returns: Kind::Integer,
+        function: char_to_code,
+    },
+    Builtin {
+        name: "CODE_TO_CHAR",
+        parameters: &[&[Kind::Integer]],
+        returns: Kind::Char,
+        function: code_to_char,
+    },
+    Builtin {
+        name: "CHAR_TO_STRING",
+        parameters: &[&[Kind::Char]],
+        returns: Kind::String,
+        function: char_to_string,
+    },
+    Builtin {
+        name: "STRING_TO_CHAR",
+        parameters: &[&[Kind::String]],
+        returns: Kind::Char,
+        function: string_to_char,
+    },
+    Builtin {
+        name: "STRING_TO_INT",
+        parameters: &[&[Kind::String]],
+        returns: Kind::Integer,
+        function: string_to_int,
+    },
+    Builtin {
+        name: "STRING_TO_REAL",
+        parameters: &[&[Kind::String]],
+        returns: Kind::Real,
+        function: string_to_real,
+    },
+    Builtin {
+        name: "INT_TO_STRING",
+        parameters: &[&[Kind::Integer]],
+        returns: Kind::String,
+        function: int_to_string,
+    },
+    Builtin {
+        name: "REAL_TO_STRING",
+        parameters: &[&[Kind::Real]],
+        returns: Kind::String,
+        function: real_to_string,
+    },
+    Builtin {
+        name: "RANDOM_INT",
+        parameters: &[&[Kind::Integer], &[Kind::Integer]],
+        returns: Kind::Integer,
+        function: random_int,
+    },
+];
+
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+impl Builtin {
+    pub fn call(&self, rng: &mut Rng, arguments: Vec<Value>, name: &Identifier) -> Result<Value> {
+        if arguments.len() != self.parameters.len() {
+            return Err(Error::new(
+                ErrorType::ArityMismatch(name.name.clone(), self.parameters.len(), arguments.len()),
+                name.span,
+            ));
+        }
+
+        for (accepted, argument) in self.parameters.iter().zip(&arguments) {
+            if !Kind::of(argument).is_some_and(|kind| accepted.contains(&kind)) {
+                return Err(mismatched_argument(argument, name));
+            }
+        }
+
+        (self.function)(rng, arguments, name)
+    }
+}
+
+fn mismatched_argument(value: &Value, name: &Identifier) -> Error {
+    Error::new(
+        ErrorType::MismatchedArgument(name.name.clone(), value.type_name()),
+        name.span,
+    )
+}
+
+fn expect_string(value: &Value, name: &Identifier) -> Result<String> {
+    match value {
+        Value::String(string) => Ok(string.clone()),
+        value => Err(mismatched_argument(value, name)),
+    }
+}
+
+fn expect_int(value: &Value, name: &Identifier) -> Result<i64> {
+    match value {
+        Value::Int(value) => Ok(*value),
+        value => Err(mismatched_argument(value, name)),
+    }
+}
+
+fn expect_float(value: &Value, name: &Identifier) -> Result<f64> {
+    match value {
+        Value::Float(value) => Ok(*value),
+        value => Err(mismatched_argument(value, name)),
+    }
+}
+
+// A String holding a single character is accepted wherever a Char is
+fn expect_char(value: &Value, name: &Identifier) -> Result<char> {
+    if let Value::Char(char) = value {
+        return Ok(*char);
+    }
+
+    let string = expect_string(value, name)?;
+    let mut chars = string.chars();
+    match (chars.next(), chars.next()) {
+        (Some(char), None) => Ok(char),
+        _ => Err(Error::new(
+            ErrorType::ExpectedCharacter(name.name.clone(), string),
+            name.span,
+        )),
+    }
+}
+
+fn len(_rng: &mut Rng, arguments: Vec<Value>, name: &Identifier) -> Result<Value> {
+    match &arguments[0] {
+        Value::Array(elements) => Ok(Value::Int(elements.borrow().len() as i64)),
+        Value::String(string) => Ok(Value::Int(string.chars().count() as i64)),
+        value => Err(mismatched_argument(value, name)),
+    }
+}
+
+fn position(_rng: &mut Rng, arguments: Vec<Value>, name: &Identifier) -> Result<Value> {
+    let string = expect_string(&arguments[0], name)?;
+    let char = expect_char(&arguments[1], name)?;
+
+    let index = string
+        .chars()
+        .position(|c| c == char)
+        .map_or(-1, |index| index as i64);
+    Ok(Value::Int(index))
+}
+
+fn substring(_rng: &mut Rng, arguments: Vec<Value>, name: &Identifier) -> Result<Value> {
+    let start = expect_int(&arguments[0], name)?;
+    let end = expect_int(&arguments[1], name)?;
+    let string = expect_string(&arguments[2], name)?;
+
+    let length = string.chars().count();
+    for index in [start, end] {
+        if index < 0 || index as usize >= length {
+            return Err(Error::new(
+                ErrorType::IndexOutOfBounds(index, length),
+                name.span,
+            ));
+        }
+    }
+
+    let substring = string
+        .chars()
+        .skip(start as usize)
+        .take((end - start + 1).max(0) as usize)
+        .collect();
+    Ok(Value::String(substring))
+}
+
+fn char_to_code(_rng: &mut Rng, arguments: Vec<Value>, name: &Identifier) -> Result<Value> {
+    let char = expect_char(&arguments[0], name)?;
+    Ok(Value::Int(char as i64))
+}
+
+fn code_to_char(_rng: &mut Rng, arguments: Vec<Value>, name: &Identifier) -> Result<Value> {
+    let code = expect_int(&arguments[0], name)?;
+    match u32::try_from(code).ok().and_then(char::from_u32) {
+        Some(char) => Ok(Value::Char(char)),
+        None => Err(Error::new(ErrorType::InvalidCharCode(code), name.span)),
+    }
+}
+
+fn char_to_string(_rng: &mut Rng, arguments: Vec<Value>, name: &Identifier) -> Result<Value> {
+    match &arguments[0] {
+        Value::Char(char) => Ok(Value::String(char.to_string())),
+        value => Err(mismatched_argument(value, name)),
+    }
+}
+
+fn string_to_char(_rng: &mut Rng, arguments: Vec<Value>, name: &Identifier) -> Result<Value> {
+    expect_string(&arguments[0], name)?;
+    expect_char(&arguments[0], name).map(Value::Char)
+}
+
+fn string_to_int(_rng: &mut Rng, arguments: Vec<Value>, name: &Identifier) -> Result<Value> {
+    let string = expect_string(&arguments[0], name)?;
+    match string.parse::<i64>() {
+        Ok(value) => Ok(Value::Int(value)),
+        Err(_) => Err(Error::new(
+            ErrorType::ConversionFailed(String::from("Integer"), string),
+            name.span,
+        )),
+    }
+}
+
+fn string_to_real(_rng: &mut Rng, arguments: Vec<Value>, name: &Identifier) -> Result<Value> {
+    let string = expect_string(&arguments[0], name)?;
+    match string.parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(Value::Float(value)),
+        _ => Err(Error::new(
+            ErrorType::ConversionFailed(String::from("Real"), string),
+            name.span,
+        )),
+    }
+}
+
+fn int_to_string(_rng: &mut Rng, arguments: Vec<Value>, name: &Identifier) -> Result<Value> {
+    let value = expect_int(&arguments[0], name)?;
+    Ok(Value::String(Value::Int(value).to_string()))
+}
+
+fn real_to_string(_rng: &mut Rng, arguments: Vec<Value>, name: &Identifier) -> Result<Value> {
+    let value = expect_float(&arguments[0], name)?;
+    Ok(Value::String(Value::Float(value).to_string()))
+}
+
+fn random_int(rng: &mut Rng, arguments: Vec<Value>, name: &Identifier) -> Result<Value> {
+    let low = expect_int(&arguments[0], name)?;
+    let high = expect_int(&arguments[1], name)?;
+
+    if low > high {
+        return Err(Error::new(ErrorType::InvalidRange(low, high), name.span));
+    }
+
+    Ok(Value::Int(rng.range_inclusive(low, high)))
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use crate::{parser::Annotation, Span, Value};
+
+use super::{
+    binding::{check_constant, lookup, Binding},
+    Result,
+};
+
+type Scope = HashMap<String, Binding>;
+
+#[derive(Debug, Default)]
+pub struct Environment {
+    globals: Scope,
+    frames: Vec<Scope>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        let mut globals: Vec<_> = self
+            .globals
+            .iter()
+            .filter_map(|(name, binding)| Some((name.clone(), binding.value()?.clone())))
+            .collect();
+        globals.sort_by(|(a, _), (b, _)| a.cmp(b));
+        globals
+    }
+
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn push_frame(&mut self) {
+        self.frames.push(Scope::new());
+    }
+
+    pub fn pop_frame(&mut self) {
+        self.frames.pop();
+    }
+
+    pub fn get(&self, name: &str, span: Span) -> Result<Value> {
+        let binding = self
+            .frames
+            .last()
+            .and_then(|frame| frame.get(name))
+            .or_else(|| self.globals.get(name));
+
+        lookup(binding, name, span)
+    }
+
+    /// The type `name` was declared with, in the scope a read would use.
+    pub fn annotation(&self, name: &str) -> Option<&Annotation> {
+        self.frames
+            .last()
+            .and_then(|frame| frame.get(name))
+            .or_else(|| self.globals.get(name))?
+            .annotation()
+    }
+
+    pub fn assign(&mut self, name: &str, value: Value, span: Span) -> Result<()> {
+        self.bind(name, value, false, span)
+    }
+
+    pub fn define_constant(&mut self, name: &str, value: Value, span: Span) -> Result<()> {
+        self.bind(name, value, true, span)
+    }
+
+    /// Binds `name` to a declared type that every later assignment must have.
+    pub fn declare(
+        &mut self,
+        name: &str,
+        annotation: &Annotation,
+        value: Option<Value>,
+        constant: bool,
+        span: Span,
+    ) -> Result<()> {
+        self.check_constant(name, span)?;
+        let binding = Binding::declare(name, annotation, value, constant, span)?;
+
+        let scope = self.frames.last_mut().unwrap_or(&mut self.globals);
+        scope.insert(name.to_string(), binding);
+        Ok(())
+    }
+
+    pub fn define_local(&mut self, name: &str, value: Value, annotation: Option<Annotation>) {
+        let scope = self.frames.last_mut().unwrap_or(&mut self.globals);
+        scope.insert(name.to_string(), Binding::parameter(value, annotation));
+    }
+
+    fn check_constant(&self, name: &str, span: Span) -> Result<()> {
+        let local = self.frames.last().and_then(|frame| frame.get(name));
+        check_constant(local, self.globals.get(name), name, span)
+    }
+
+    fn bind(&mut self, name: &str, value: Value, constant: bool, span: Span) -> Result<()> {
+        self.check_constant(name, span)?;
+
+        let scope = self.frames.last_mut().unwrap_or(&mut self.globals);
+        match scope.get_mut(name) {
+            Some(binding) => binding.assign(name, value, constant, span)?,
+            None => {
+                scope.insert(name.to_string(), Binding::new(value, constant));
+            }
+        }
+        Ok(())
+    }
+}
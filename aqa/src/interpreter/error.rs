@@ -1,28 +1,110 @@
 use std::fmt;
 
+use crate::Span;
+
 #[derive(Debug)]
 pub enum ErrorType {
     MismatchedType,
+    UndefinedVariable(String),
+    ConstantReassignment(String),
+    NonBoolCondition(String),
+    ExpectedInt(String),
+    ZeroStep,
+    NotIterable(String),
+    UndefinedSubroutine(String),
+    ArityMismatch(String, usize, usize),
+    NoReturnValue(String),
+    StackOverflow(String),
+    IndexOutOfBounds(i64, usize),
+    NotIndexable(String),
+    MismatchedArgument(String, String),
+    NotARecord(String),
+    UnknownField(String, String),
+    DivisionByZero,
+    IntegerOverflow,
+    InputExhausted,
+    ExpectedCharacter(String, String),
+    InvalidCharCode(i64),
+    ConversionFailed(String, String),
+    InvalidRange(i64, i64),
+    DeclaredType(String, String, String),
+    UnknownType(String),
 }
 
 #[derive(Debug)]
 pub struct Error {
     pub error_type: ErrorType,
+    pub span: Span,
 }
 
 impl Error {
-    pub fn new(error_type: ErrorType) -> Self {
-        Self { error_type }
+    pub fn new(error_type: ErrorType, span: Span) -> Self {
+        Self { error_type, span }
     }
 }
 
-impl fmt::Display for Error {
+impl fmt::Display for ErrorType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let error_message = match &self.error_type {
+        let message = match self {
             ErrorType::MismatchedType => String::from("Mismatched Type"),
+            ErrorType::UndefinedVariable(name) => format!("Undefined variable '{}'", name),
+            ErrorType::ConstantReassignment(name) => format!("Cannot reassign constant '{}'", name),
+            ErrorType::NonBoolCondition(value) => {
+                format!("Condition must be a Boolean, found '{}'", value)
+            }
+            ErrorType::ExpectedInt(value) => format!("Expected an Integer, found '{}'", value),
+            ErrorType::ZeroStep => String::from("FOR loop STEP cannot be 0"),
+            ErrorType::NotIterable(value) => format!("Cannot iterate over '{}'", value),
+            ErrorType::UndefinedSubroutine(name) => format!("Undefined subroutine '{}'", name),
+            ErrorType::ArityMismatch(name, expected, found) => format!(
+                "'{}' takes {} argument(s) but {} were given",
+                name, expected, found
+            ),
+            ErrorType::NoReturnValue(name) => format!("'{}' did not return a value", name),
+            ErrorType::StackOverflow(name) => format!("Stack overflow when calling '{}'", name),
+            ErrorType::IndexOutOfBounds(index, length) => {
+                format!("Index {} is out of bounds for length {}", index, length)
+            }
+            ErrorType::NotIndexable(value) => format!("Cannot index into '{}'", value),
+            ErrorType::MismatchedArgument(name, value) => {
+                format!("'{}' cannot accept '{}'", name, value)
+            }
+            ErrorType::NotARecord(value) => format!("'{}' is not a record", value),
+            ErrorType::UnknownField(record, field) => {
+                format!("Record '{}' has no field '{}'", record, field)
+            }
+            ErrorType::DivisionByZero => String::from("Division by zero"),
+            ErrorType::IntegerOverflow => String::from("Integer overflow"),
+            ErrorType::InputExhausted => String::from("No more input available for USERINPUT"),
+            ErrorType::ExpectedCharacter(name, string) => {
+                format!("'{}' expects a single character, found '{}'", name, string)
+            }
+            ErrorType::InvalidCharCode(code) => format!("{} is not a valid character code", code),
+            ErrorType::ConversionFailed(target, string) => {
+                format!("Cannot convert '{}' to {}", string, target)
+            }
+            ErrorType::InvalidRange(low, high) => {
+                format!("Invalid range: {} is greater than {}", low, high)
+            }
+            ErrorType::DeclaredType(name, declared, found) => {
+                format!("Expected {} for '{}', found {}", declared, name, found)
+            }
+            ErrorType::UnknownType(name) => format!("Unknown type '{}'", name),
         };
 
-        write!(f, "{}", error_message)
+        write!(f, "{}", message)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "at line {}, column {}: {}",
+            self.span.start().line(),
+            self.span.start().column(),
+            self.error_type
+        )
     }
 }
 
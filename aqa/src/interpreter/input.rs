@@ -0,0 +1,36 @@
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead},
+};
+
+/// Source of lines read by `USERINPUT`.
+///
+/// Returning `None` means no more input is available.
+pub trait InputSource {
+    fn input(&mut self) -> Option<String>;
+}
+
+/// Reads each line from standard input.
+#[derive(Debug, Default)]
+pub struct StdinSource;
+
+impl InputSource for StdinSource {
+    fn input(&mut self) -> Option<String> {
+        let mut line = String::new();
+        match io::stdin().lock().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => {
+                let trimmed = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(trimmed);
+                Some(line)
+            }
+        }
+    }
+}
+
+/// Replays a scripted queue of lines, e.g. for tests or embedding hosts.
+impl InputSource for VecDeque<String> {
+    fn input(&mut self) -> Option<String> {
+        self.pop_front()
+    }
+}
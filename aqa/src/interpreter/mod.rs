@@ -1,26 +1,814 @@
-use crate::{parser::ExprType, scanner::TokenType, Value};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
+use crate::{
+    parser::{
+        self, Annotation, Expr, ExprType, Identifier, RecordDefinition, Stmt, StmtType, Subroutine,
+    },
+    scanner::TokenType,
+    Program, Record, Span, Value,
+};
+
+mod binding;
+pub(crate) mod builtins;
+mod environment;
 mod error;
+mod input;
+mod output;
+mod random;
+mod vm;
+
+pub use environment::*;
 pub use error::*;
+pub use input::*;
+pub use output::*;
+pub use vm::Vm;
+
+use random::Rng;
+
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 256;
+
+// Each call nests several native frames, which are large in debug builds, so
+// the stack is extended on the heap when less than `STACK_RED_ZONE` is left
+const STACK_RED_ZONE: usize = 256 * 1024;
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+
+/// Executes programs while keeping variables, subroutines and records alive
+/// between calls, so a host can feed it one program or statement at a time.
+pub struct Interpreter<'a> {
+    environment: Environment,
+    subroutines: HashMap<String, Rc<Subroutine>>,
+    records: HashMap<String, Rc<RecordDefinition>>,
+    output: &'a mut dyn OutputSink,
+    input: &'a mut dyn InputSource,
+    rng: Rng,
+    max_call_depth: usize,
+    trace: Option<Vec<Span>>,
+}
+
+enum Flow {
+    Next(Option<Value>),
+    Return(Option<Value>),
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(output: &'a mut dyn OutputSink, input: &'a mut dyn InputSource) -> Self {
+        Self {
+            environment: Environment::new(),
+            subroutines: HashMap::new(),
+            records: HashMap::new(),
+            output,
+            input,
+            rng: Rng::new(None),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            trace: None,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+
+    pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+
+    /// Records the span of every statement executed from now on.
+    pub fn with_trace(mut self) -> Self {
+        self.trace = Some(Vec::new());
+        self
+    }
+
+    pub fn trace(&self) -> Option<&[Span]> {
+        self.trace.as_deref()
+    }
+
+    /// Global variables and constants, sorted by name.
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        self.environment.globals()
+    }
+
+    /// Forgets every variable, subroutine and record while keeping the I/O
+    /// handles and limits.
+    pub fn reset(&mut self) {
+        self.environment = Environment::new();
+        self.subroutines.clear();
+        self.records.clear();
+    }
+
+    /// Runs `program` against the current state and returns the value of its
+    /// last expression statement.
+    ///
+    /// Subroutines and records are declared before anything runs, so they can
+    /// be called above their definition.
+    pub fn execute(&mut self, program: &Program) -> crate::Result<Option<Value>> {
+        let records = &self.records;
+        check_types_exist(program, |record| records.contains_key(record))?;
+
+        for stmt in &program.statements {
+            self.declare(stmt);
+        }
+
+        let mut last_value = None;
+
+        for stmt in &program.statements {
+            match self.execute_stmt(stmt)? {
+                Flow::Next(value) => last_value = value,
+                Flow::Return(value) => return Ok(value),
+            }
+        }
+
+        Ok(last_value)
+    }
+
+    /// Runs one statement of a program, e.g. to step through it.
+    pub fn execute_statement(&mut self, stmt: &Stmt) -> crate::Result<Option<Value>> {
+        match self.execute_stmt(stmt)? {
+            Flow::Next(value) | Flow::Return(value) => Ok(value),
+        }
+    }
+
+    fn declare(&mut self, stmt: &Stmt) {
+        match &stmt.stmt_type {
+            StmtType::Subroutine(subroutine) => {
+                self.subroutines
+                    .insert(subroutine.name.name.clone(), subroutine.clone());
+            }
+            StmtType::Record(record) => {
+                self.records
+                    .insert(record.name.name.clone(), record.clone());
+            }
+            _ => {}
+        }
+    }
+
+    fn execute_stmt(&mut self, stmt: &Stmt) -> Result<Flow> {
+        if let Some(trace) = &mut self.trace {
+            trace.push(stmt.span);
+        }
+
+        match &stmt.stmt_type {
+            StmtType::Expression(Expr {
+                expr_type: ExprType::Call(name, arguments),
+                ..
+            }) => Ok(Flow::Next(self.call(name, arguments)?)),
+            StmtType::Expression(expr) => Ok(Flow::Next(Some(self.evaluate(expr)?))),
+            StmtType::Assign(name, expr) => {
+                let value = self.evaluate(expr)?;
+                self.environment.assign(&name.name, value, name.span)?;
+                Ok(Flow::Next(None))
+            }
+            StmtType::AssignIndex(array, index, expr) => {
+                let (array_value, place) = self.evaluate_place(array)?;
+                let index_value = expect_int(self.evaluate(index)?, index.span)?;
+                let value = self.evaluate(expr)?;
+
+                let element = place.and_then(Place::element);
+                store_index(
+                    array_value,
+                    index_value,
+                    value,
+                    element,
+                    array.span,
+                    index.span,
+                )?;
+                Ok(Flow::Next(None))
+            }
+            StmtType::AssignField(record, field, expr) => {
+                let record = expect_record(self.evaluate(record)?, record.span)?;
+                let value = self.evaluate(expr)?;
+
+                let record_name = record.borrow().name.clone();
+                let definition = self.records.get(&record_name);
+                store_field(&record, field, value, definition)?;
+
+                Ok(Flow::Next(None))
+            }
+            StmtType::Output(exprs) => {
+                let mut line = String::new();
+                for expr in exprs {
+                    line += &self.evaluate(expr)?.to_string();
+                }
+                self.output.output(line);
+                Ok(Flow::Next(None))
+            }
+            StmtType::If(condition, then_branch, else_branch) => {
+                let branch = if expect_bool(self.evaluate(condition)?, condition.span)? {
+                    then_branch
+                } else {
+                    else_branch
+                };
+                self.execute_block(branch)
+            }
+            StmtType::For(name, start, end, step, body) => {
+                let start = expect_int(self.evaluate(start)?, start.span)?;
+                let end = expect_int(self.evaluate(end)?, end.span)?;
+                let step = match step {
+                    Some(step) => {
+                        let value = expect_int(self.evaluate(step)?, step.span)?;
+                        if value == 0 {
+                            return Err(Error::new(ErrorType::ZeroStep, step.span));
+                        }
+                        value
+                    }
+                    None => 1,
+                };
+
+                let mut counter = start;
+                while (step > 0 && counter <= end) || (step < 0 && counter >= end) {
+                    self.environment
+                        .assign(&name.name, Value::Int(counter), name.span)?;
+                    if let Flow::Return(value) = self.execute_block(body)? {
+                        return Ok(Flow::Return(value));
+                    }
+                    // The counter cannot pass the end of the Integer range
+                    counter = match counter.checked_add(step) {
+                        Some(counter) => counter,
+                        None => break,
+                    };
+                }
+
+                Ok(Flow::Next(None))
+            }
+            StmtType::ForIn(name, iterable, body) => {
+                let items: Vec<Value> = match self.evaluate(iterable)? {
+                    Value::String(string) => string.chars().map(Value::Char).collect(),
+                    Value::Array(elements) => elements.borrow().clone(),
+                    value => {
+                        return Err(Error::new(
+                            ErrorType::NotIterable(value.type_name()),
+                            iterable.span,
+                        ))
+                    }
+                };
+
+                for item in items {
+                    self.environment.assign(&name.name, item, name.span)?;
+                    if let Flow::Return(value) = self.execute_block(body)? {
+                        return Ok(Flow::Return(value));
+                    }
+                }
+
+                Ok(Flow::Next(None))
+            }
+            StmtType::While(condition, body) => {
+                while expect_bool(self.evaluate(condition)?, condition.span)? {
+                    if let Flow::Return(value) = self.execute_block(body)? {
+                        return Ok(Flow::Return(value));
+                    }
+                }
+
+                Ok(Flow::Next(None))
+            }
+            StmtType::Repeat(body, condition) => {
+                loop {
+                    if let Flow::Return(value) = self.execute_block(body)? {
+                        return Ok(Flow::Return(value));
+                    }
+                    if expect_bool(self.evaluate(condition)?, condition.span)? {
+                        break;
+                    }
+                }
+
+                Ok(Flow::Next(None))
+            }
+            StmtType::Declare(name, annotation, expr) => {
+                let value = match expr {
+                    Some(expr) => Some(self.evaluate(expr)?),
+                    None => None,
+                };
+                self.environment
+                    .declare(&name.name, annotation, value, false, name.span)?;
+                Ok(Flow::Next(None))
+            }
+            StmtType::Constant(name, annotation, expr) => {
+                let value = self.evaluate(expr)?;
+                match annotation {
+                    Some(annotation) => self.environment.declare(
+                        &name.name,
+                        annotation,
+                        Some(value),
+                        true,
+                        name.span,
+                    )?,
+                    None => self
+                        .environment
+                        .define_constant(&name.name, value, name.span)?,
+                }
+                Ok(Flow::Next(None))
+            }
+            StmtType::Subroutine(_) | StmtType::Record(_) => {
+                self.declare(stmt);
+                Ok(Flow::Next(None))
+            }
+            StmtType::Return(expr) => {
+                let value = match expr {
+                    Some(expr) => Some(self.evaluate(expr)?),
+                    None => None,
+                };
+                Ok(Flow::Return(value))
+            }
+        }
+    }
+
+    fn execute_block(&mut self, block: &[Stmt]) -> Result<Flow> {
+        for stmt in block {
+            if let Flow::Return(value) = self.execute_stmt(stmt)? {
+                return Ok(Flow::Return(value));
+            }
+        }
+
+        Ok(Flow::Next(None))
+    }
+
+    fn call(&mut self, name: &Identifier, arguments: &[Expr]) -> Result<Option<Value>> {
+        let subroutine = match self.subroutines.get(&name.name) {
+            Some(subroutine) => subroutine.clone(),
+            None => {
+                if let Some(record) = self.records.get(&name.name).cloned() {
+                    return self.construct_record(&record, name, arguments).map(Some);
+                }
+
+                if let Some(builtin) = builtins::lookup(&name.name) {
+                    let mut values = Vec::with_capacity(arguments.len());
+                    for argument in arguments {
+                        values.push(self.evaluate(argument)?);
+                    }
+                    return builtin.call(&mut self.rng, values, name).map(Some);
+                }
+
+                return Err(Error::new(
+                    ErrorType::UndefinedSubroutine(name.name.clone()),
+                    name.span,
+                ));
+            }
+        };
+
+        if arguments.len() != subroutine.parameters.len() {
+            return Err(Error::new(
+                ErrorType::ArityMismatch(
+                    name.name.clone(),
+                    subroutine.parameters.len(),
+                    arguments.len(),
+                ),
+                name.span,
+            ));
+        }
+
+        if self.environment.depth() >= self.max_call_depth {
+            return Err(Error::new(
+                ErrorType::StackOverflow(name.name.clone()),
+                name.span,
+            ));
+        }
+
+        let mut values = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            values.push(self.evaluate(argument)?);
+        }
+        let values = values
+            .into_iter()
+            .zip(&subroutine.parameters)
+            .zip(arguments)
+            .map(
+                |((value, parameter), argument)| match &parameter.annotation {
+                    Some(annotation) => {
+                        conform(value, annotation, &parameter.name.name, argument.span)
+                    }
+                    None => Ok(value),
+                },
+            )
+            .collect::<Result<Vec<_>>>()?;
+
+        self.environment.push_frame();
+        for (parameter, value) in subroutine.parameters.iter().zip(values) {
+            self.environment.define_local(
+                &parameter.name.name,
+                value,
+                parameter.annotation.clone(),
+            );
+        }
+
+        let result = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, || {
+            self.execute_block(&subroutine.body)
+        });
+        self.environment.pop_frame();
+
+        match (result?, &subroutine.returns) {
+            (Flow::Return(Some(value)), Some(returns)) => {
+                conform(value, returns, &name.name, name.span).map(Some)
+            }
+            (Flow::Return(value), _) => Ok(value),
+            (Flow::Next(_), _) => Ok(None),
+        }
+    }
+
+    fn construct_record(
+        &mut self,
+        record: &RecordDefinition,
+        name: &Identifier,
+        arguments: &[Expr],
+    ) -> Result<Value> {
+        if arguments.len() != record.fields.len() {
+            return Err(Error::new(
+                ErrorType::ArityMismatch(name.name.clone(), record.fields.len(), arguments.len()),
+                name.span,
+            ));
+        }
+
+        let mut values = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            values.push(self.evaluate(argument)?);
+        }
+
+        let mut fields = Vec::with_capacity(arguments.len());
+        for ((field, value), argument) in record.fields.iter().zip(values).zip(arguments) {
+            let value = conform(value, &field.annotation, &field.name.name, argument.span)?;
+            fields.push((field.name.name.clone(), value));
+        }
 
-pub fn evaluate(expr: ExprType) -> Result<Value> {
-    match expr {
-        ExprType::Unary(operator, right) => {
-            let right_value = evaluate(*right)?;
+        Ok(Value::Record(Rc::new(RefCell::new(Record {
+            name: record.name.name.clone(),
+            fields,
+        }))))
+    }
 
-            match (operator.token_type, right_value) {
-                (TokenType::Subtract, Value::Int(value)) => Ok(Value::Int(-value)),
-                (TokenType::Subtract, Value::Float(value)) => Ok(Value::Float(-value)),
-                (TokenType::LogicalNot, Value::Bool(value)) => Ok(Value::Bool(!value)),
-                _ => Err(Error::new(ErrorType::MismatchedType)),
+    // Evaluates the array of an element store, along with the type declared
+    // for it by the variable or field it was read from
+    fn evaluate_place(&mut self, expr: &Expr) -> Result<(Value, Option<Place>)> {
+        match &expr.expr_type {
+            ExprType::Variable(name) => {
+                let value = self.environment.get(&name.name, name.span)?;
+                let place = Place::new(&name.name, self.environment.annotation(&name.name));
+                Ok((value, place))
+            }
+            ExprType::Field(record, field) => {
+                let record = expect_record(self.evaluate(record)?, record.span)?;
+                let value = load_field(&record.borrow(), field)?;
+                let definition = self.records.get(&record.borrow().name);
+                let place = Place::new(&field.name, field_annotation(definition, field));
+                Ok((value, place))
             }
+            ExprType::Index(array, index) => {
+                let (array_value, place) = self.evaluate_place(array)?;
+                let index_value = expect_int(self.evaluate(index)?, index.span)?;
+                let value = load_index(array_value, index_value, array.span, index.span)?;
+                Ok((value, place.and_then(Place::element)))
+            }
+            _ => Ok((self.evaluate(expr)?, None)),
         }
-        ExprType::Literal(value) => Ok(value),
-        ExprType::Binary(left, operator, right) => {
-            let left_value = evaluate(*left)?;
-            let right_value = evaluate(*right)?;
+    }
 
-            include!(concat!(env!("OUT_DIR"), "/binary.rs"))
+    fn evaluate(&mut self, expr: &Expr) -> Result<Value> {
+        match &expr.expr_type {
+            ExprType::Unary(operator, right) => {
+                let right_value = self.evaluate(right)?;
+                unary(&operator.token_type, right_value, expr.span)
+            }
+            ExprType::Literal(value) => Ok(value.clone()),
+            ExprType::Variable(name) => self.environment.get(&name.name, name.span),
+            ExprType::Call(name, arguments) => match self.call(name, arguments)? {
+                Some(value) => Ok(value),
+                None => Err(Error::new(
+                    ErrorType::NoReturnValue(name.name.clone()),
+                    expr.span,
+                )),
+            },
+            ExprType::UserInput => match self.input.input() {
+                Some(line) => Ok(Value::String(line)),
+                None => Err(Error::new(ErrorType::InputExhausted, expr.span)),
+            },
+            ExprType::Array(elements) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(self.evaluate(element)?);
+                }
+                Ok(Value::Array(Rc::new(RefCell::new(values))))
+            }
+            ExprType::Index(array, index) => {
+                let array_value = self.evaluate(array)?;
+                let index_value = expect_int(self.evaluate(index)?, index.span)?;
+                load_index(array_value, index_value, array.span, index.span)
+            }
+            ExprType::Field(record, field) => {
+                let record = expect_record(self.evaluate(record)?, record.span)?;
+                let record = record.borrow();
+                load_field(&record, field)
+            }
+            ExprType::Logical(left, operator, right) => {
+                let left_value = match self.evaluate(left)? {
+                    Value::Bool(value) => value,
+                    _ => return Err(Error::new(ErrorType::MismatchedType, left.span)),
+                };
+
+                let short_circuit = match operator.token_type {
+                    TokenType::LogicalAnd => !left_value,
+                    _ => left_value,
+                };
+                if short_circuit {
+                    return Ok(Value::Bool(left_value));
+                }
+
+                match self.evaluate(right)? {
+                    Value::Bool(value) => Ok(Value::Bool(value)),
+                    _ => Err(Error::new(ErrorType::MismatchedType, right.span)),
+                }
+            }
+            ExprType::Binary(left, operator, right) => {
+                let left_value = self.evaluate(left)?;
+                let right_value = self.evaluate(right)?;
+
+                binary(
+                    left_value,
+                    &operator.token_type,
+                    right_value,
+                    right.span,
+                    expr.span,
+                )
+            }
         }
     }
 }
+
+/// The type declared for a value by the variable or field that holds it.
+#[derive(Debug)]
+struct Place {
+    name: String,
+    annotation: Annotation,
+}
+
+impl Place {
+    fn new(name: &str, annotation: Option<&Annotation>) -> Option<Self> {
+        Some(Self {
+            name: name.to_string(),
+            annotation: annotation?.clone(),
+        })
+    }
+
+    // Elements of an array are declared by the array's own declaration
+    fn element(self) -> Option<Self> {
+        match self.annotation {
+            Annotation::Array(element) => Some(Self {
+                name: self.name,
+                annotation: *element,
+            }),
+            _ => None,
+        }
+    }
+}
+
+// Annotations are checked before anything runs, so a misspelt type is not
+// reported as a value of the wrong type
+fn check_types_exist(program: &Program, is_record: impl Fn(&str) -> bool) -> Result<()> {
+    match parser::unknown_types(&program.statements, is_record)
+        .into_iter()
+        .next()
+    {
+        Some((type_name, name)) => Err(Error::new(ErrorType::UnknownType(type_name), name.span)),
+        None => Ok(()),
+    }
+}
+
+fn load_index(array: Value, index: i64, array_span: Span, index_span: Span) -> Result<Value> {
+    match array {
+        Value::Array(elements) => {
+            let elements = elements.borrow();
+            let index = check_bounds(index, elements.len(), index_span)?;
+            Ok(elements[index].clone())
+        }
+        value => Err(Error::new(
+            ErrorType::NotIndexable(value.type_name()),
+            array_span,
+        )),
+    }
+}
+
+// The value is checked before the array is borrowed, as it may hold the
+// array itself
+fn store_index(
+    array: Value,
+    index: i64,
+    value: Value,
+    element: Option<Place>,
+    array_span: Span,
+    index_span: Span,
+) -> Result<()> {
+    let elements = match array {
+        Value::Array(elements) => elements,
+        value => {
+            return Err(Error::new(
+                ErrorType::NotIndexable(value.type_name()),
+                array_span,
+            ))
+        }
+    };
+    let index = check_bounds(index, elements.borrow().len(), index_span)?;
+
+    let value = match element {
+        Some(element) => conform(value, &element.annotation, &element.name, array_span)?,
+        None => value,
+    };
+    elements.borrow_mut()[index] = value;
+    Ok(())
+}
+
+fn load_field(record: &Record, field: &Identifier) -> Result<Value> {
+    match record.fields.iter().find(|(name, _)| *name == field.name) {
+        Some((_, value)) => Ok(value.clone()),
+        None => Err(Error::new(
+            ErrorType::UnknownField(record.name.clone(), field.name.clone()),
+            field.span,
+        )),
+    }
+}
+
+fn expect_record(value: Value, span: Span) -> Result<Rc<RefCell<Record>>> {
+    match value {
+        Value::Record(record) => Ok(record),
+        value => Err(Error::new(ErrorType::NotARecord(value.type_name()), span)),
+    }
+}
+
+fn check_bounds(index: i64, length: usize, span: Span) -> Result<usize> {
+    if index >= 0 && (index as usize) < length {
+        Ok(index as usize)
+    } else {
+        Err(Error::new(ErrorType::IndexOutOfBounds(index, length), span))
+    }
+}
+
+fn expect_bool(value: Value, span: Span) -> Result<bool> {
+    match value {
+        Value::Bool(value) => Ok(value),
+        value => Err(Error::new(
+            ErrorType::NonBoolCondition(value.type_name()),
+            span,
+        )),
+    }
+}
+
+fn expect_int(value: Value, span: Span) -> Result<i64> {
+    match value {
+        Value::Int(value) => Ok(value),
+        value => Err(Error::new(ErrorType::ExpectedInt(value.type_name()), span)),
+    }
+}
+
+// The value is checked before the record is borrowed, as it may hold the
+// record itself
+fn store_field(
+    record: &RefCell<Record>,
+    field: &Identifier,
+    value: Value,
+    definition: Option<&Rc<RecordDefinition>>,
+) -> Result<()> {
+    let record_name = record.borrow().name.clone();
+    if !record
+        .borrow()
+        .fields
+        .iter()
+        .any(|(name, _)| *name == field.name)
+    {
+        return Err(Error::new(
+            ErrorType::UnknownField(record_name, field.name.clone()),
+            field.span,
+        ));
+    }
+
+    let value = match field_annotation(definition, field) {
+        Some(annotation) => conform(value, annotation, &field.name, field.span)?,
+        None => value,
+    };
+
+    let mut record = record.borrow_mut();
+    if let Some((_, slot)) = record
+        .fields
+        .iter_mut()
+        .find(|(name, _)| *name == field.name)
+    {
+        *slot = value;
+    }
+    Ok(())
+}
+
+fn field_annotation<'r>(
+    record: Option<&'r Rc<RecordDefinition>>,
+    field: &Identifier,
+) -> Option<&'r Annotation> {
+    record?
+        .fields
+        .iter()
+        .find(|definition| definition.name.name == field.name)
+        .map(|definition| &definition.annotation)
+}
+
+// Checks a value against its declared type, widening an Integer declared as
+// Real and narrowing a one-character String declared as Char
+fn conform(value: Value, annotation: &Annotation, name: &str, span: Span) -> Result<Value> {
+    if matches_annotation(&value, annotation) {
+        Ok(convert(value, annotation))
+    } else {
+        Err(Error::new(
+            ErrorType::DeclaredType(name.to_string(), annotation.to_string(), describe(&value)),
+            span,
+        ))
+    }
+}
+
+// Array elements are converted in place, so every alias of the array sees
+// the declared type
+fn convert(value: Value, annotation: &Annotation) -> Value {
+    match (annotation, value) {
+        (Annotation::Real, Value::Int(value)) => Value::Float(value as f64),
+        (Annotation::Char, Value::String(string)) => match single_char(&string) {
+            Some(char) => Value::Char(char),
+            None => Value::String(string),
+        },
+        (Annotation::Array(element), Value::Array(elements)) => {
+            let length = elements.borrow().len();
+            for index in 0..length {
+                let item = elements.borrow()[index].clone();
+                let item = convert(item, element);
+                elements.borrow_mut()[index] = item;
+            }
+            Value::Array(elements)
+        }
+        (_, value) => value,
+    }
+}
+
+fn single_char(string: &str) -> Option<char> {
+    let mut chars = string.chars();
+    match (chars.next(), chars.next()) {
+        (Some(char), None) => Some(char),
+        _ => None,
+    }
+}
+
+fn matches_annotation(value: &Value, annotation: &Annotation) -> bool {
+    match (annotation, value) {
+        (Annotation::Integer, Value::Int(_))
+        | (Annotation::Real, Value::Int(_) | Value::Float(_))
+        | (Annotation::Boolean, Value::Bool(_))
+        | (Annotation::String, Value::String(_)) => true,
+        (Annotation::Char, Value::Char(_)) => true,
+        (Annotation::Char, Value::String(string)) => single_char(string).is_some(),
+        (Annotation::Array(element), Value::Array(elements)) => elements
+            .borrow()
+            .iter()
+            .all(|value| matches_annotation(value, element)),
+        (Annotation::Record(name), Value::Record(record)) => record.borrow().name == *name,
+        _ => false,
+    }
+}
+
+// Names arrays by their element type where they all share one
+fn describe(value: &Value) -> String {
+    describe_within(value, &mut Vec::new())
+}
+
+// An array inside itself is only named once
+fn describe_within(value: &Value, open: &mut Vec<*const ()>) -> String {
+    if let Value::Array(elements) = value {
+        let pointer = Rc::as_ptr(elements) as *const ();
+        if !open.contains(&pointer) {
+            open.push(pointer);
+            let elements = elements.borrow();
+            let types: Vec<_> = elements
+                .iter()
+                .map(|element| describe_within(element, open))
+                .collect();
+            open.pop();
+
+            if let Some(first) = types.first() {
+                if types.iter().all(|other| other == first) {
+                    return format!("{}[]", first);
+                }
+            }
+        }
+    }
+    value.type_name()
+}
+
+fn unary(operator: &TokenType, right_value: Value, span: Span) -> Result<Value> {
+    match (operator, right_value) {
+        (TokenType::Subtract, Value::Int(value)) => value
+            .checked_neg()
+            .map(Value::Int)
+            .ok_or(Error::new(ErrorType::IntegerOverflow, span)),
+        (TokenType::Subtract, Value::Float(value)) => Ok(Value::Float(-value)),
+        (TokenType::LogicalNot, Value::Bool(value)) => Ok(Value::Bool(!value)),
+        _ => Err(Error::new(ErrorType::MismatchedType, span)),
+    }
+}
+
+fn binary(
+    left_value: Value,
+    operator: &TokenType,
+    right_value: Value,
+    right_span: Span,
+    span: Span,
+) -> Result<Value> {
+    include!(concat!(env!("OUT_DIR"), "/binary.rs"))
+}
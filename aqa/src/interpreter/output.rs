@@ -0,0 +1,27 @@
+use std::io::{self, Write};
+
+/// Destination for text produced by `OUTPUT` statements.
+///
+/// Each call to [`OutputSink::output`] receives one complete line, without a
+/// trailing newline.
+pub trait OutputSink {
+    fn output(&mut self, line: String);
+}
+
+/// Writes each line to standard output.
+#[derive(Debug, Default)]
+pub struct StdoutSink;
+
+impl OutputSink for StdoutSink {
+    fn output(&mut self, line: String) {
+        let mut stdout = io::stdout().lock();
+        let _ = writeln!(stdout, "{}", line);
+    }
+}
+
+/// Captures every line, e.g. for tests or embedding hosts.
+impl OutputSink for Vec<String> {
+    fn output(&mut self, line: String) {
+        self.push(line);
+    }
+}
@@ -0,0 +1,42 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+/// A small SplitMix64 generator, so programs can be replayed from a seed.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: Option<u64>) -> Self {
+        let state = seed.unwrap_or_else(|| RandomState::new().build_hasher().finish());
+        Self { state }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniformly distributed integer in `low..=high`.
+    pub fn range_inclusive(&mut self, low: i64, high: i64) -> i64 {
+        let span = high.wrapping_sub(low) as u64;
+        if span == u64::MAX {
+            return self.next_u64() as i64;
+        }
+
+        let count = span + 1;
+        let limit = u64::MAX - (u64::MAX % count);
+        loop {
+            let value = self.next_u64();
+            if value < limit {
+                return low.wrapping_add((value % count) as i64);
+            }
+        }
+    }
+}
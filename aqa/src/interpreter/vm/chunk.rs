@@ -0,0 +1,135 @@
+use std::rc::Rc;
+
+use crate::{
+    interpreter::builtins::Builtin,
+    parser::{Annotation, Identifier, Parameter, RecordDefinition},
+    scanner::TokenType,
+    Span, Value,
+};
+
+// Operands are indexes into the tables of `Bytecode`, or into `Function::spans`
+// for the location an error is reported at.
+#[derive(Debug, Clone, Copy)]
+pub enum Instruction {
+    Constant(usize),
+    Pop,
+    PopN(usize),
+
+    // (global, span)
+    LoadGlobal(usize, usize),
+    StoreGlobal(usize, usize),
+    DefineGlobalConstant(usize, usize),
+    // (slot, global, span); an unassigned slot falls back to the global
+    LoadLocal(usize, usize, usize),
+    StoreLocal(usize, usize, usize),
+    DefineLocalConstant(usize, usize, usize),
+    // Binds a declared type without a value, before any value is stored
+    // (global, annotation, span)
+    DeclareGlobal(usize, usize, usize),
+    // (slot, global, annotation, span)
+    DeclareLocal(usize, usize, usize, usize),
+
+    UserInput(usize),
+    // OUTPUT formats each value as soon as it is evaluated
+    Stringify,
+    Output(usize),
+
+    Array(usize),
+    // (array span, index span)
+    Index(usize, usize),
+    StoreIndex(usize, usize),
+    // (field, record span)
+    Field(usize, usize),
+    StoreField(usize),
+    // Element stores check the value against the type declared by the
+    // variable or field the array was read from, kept on a stack of places
+    // (slot, global)
+    LocalPlace(usize, usize),
+    // (global)
+    GlobalPlace(usize),
+    // (field); the record is left on the stack
+    FieldPlace(usize),
+    ElementPlace,
+    NoPlace,
+    ExpectInt(usize),
+    ExpectRecord(usize),
+
+    // (operator, span)
+    Unary(usize, usize),
+    // (operator, right span, span)
+    Binary(usize, usize, usize),
+    // (is AND, target, left span); leaves the left operand when it decides the result
+    ShortCircuit(bool, usize, usize),
+    ExpectLogical(usize),
+
+    Jump(usize),
+    // (target, condition span)
+    JumpIfFalse(usize, usize),
+
+    // Stack holds [counter, end, step]
+    CheckStep(usize),
+    ForTest(usize),
+    ForCounter,
+    // Jumps to the target when the counter would overflow
+    ForIncrement(usize),
+    // Stack holds [items, next index]
+    IntoIterable(usize),
+    ForInNext(usize),
+
+    Declare(usize),
+    // (call site)
+    PrepareCall(usize),
+    Call(usize),
+    // (call site, span)
+    ExpectReturn(usize, usize),
+    Return,
+    ReturnNone,
+
+    SetLast,
+    SetLastReturned,
+    ClearLast,
+    Trace(usize),
+    Halt,
+}
+
+#[derive(Debug, Default)]
+pub struct Function {
+    pub arity: usize,
+    pub parameters: Vec<Parameter>,
+    pub returns: Option<Annotation>,
+    pub local_count: usize,
+    pub code: Vec<Instruction>,
+    pub spans: Vec<Span>,
+}
+
+#[derive(Debug)]
+pub struct CallSite {
+    pub name: Identifier,
+    pub callee: usize,
+    pub arity: usize,
+    pub arguments: Vec<Span>,
+    pub builtin: Option<&'static Builtin>,
+}
+
+#[derive(Debug)]
+pub enum Declaration {
+    // (callee, function)
+    Subroutine(usize, usize),
+    // (callee, record)
+    Record(usize, usize),
+}
+
+/// A compiled program. Function 0 is the top level.
+#[derive(Debug, Default)]
+pub struct Bytecode {
+    pub functions: Vec<Function>,
+    pub constants: Vec<Value>,
+    pub globals: Vec<String>,
+    pub callees: Vec<String>,
+    pub calls: Vec<CallSite>,
+    pub fields: Vec<Identifier>,
+    pub operators: Vec<TokenType>,
+    pub annotations: Vec<Annotation>,
+    pub records: Vec<Rc<RecordDefinition>>,
+    pub declarations: Vec<Declaration>,
+}
@@ -0,0 +1,530 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    interpreter::builtins,
+    parser::{Annotation, Expr, ExprType, Identifier, Stmt, StmtType, Subroutine},
+    scanner::TokenType,
+    Span, Value,
+};
+
+use super::chunk::{Bytecode, CallSite, Declaration, Function, Instruction};
+
+pub struct Compiler {
+    bytecode: Bytecode,
+    globals: HashMap<String, usize>,
+    callees: HashMap<String, usize>,
+    declarations: HashMap<usize, usize>,
+    trace: bool,
+}
+
+// The function being compiled, with the slots of its local variables
+struct Scope {
+    function: Function,
+    locals: Option<HashMap<String, usize>>,
+}
+
+impl Compiler {
+    pub fn new(trace: bool) -> Self {
+        Self {
+            bytecode: Bytecode::default(),
+            globals: HashMap::new(),
+            callees: HashMap::new(),
+            declarations: HashMap::new(),
+            trace,
+        }
+    }
+
+    pub fn compile(mut self, program: &[Stmt]) -> Bytecode {
+        self.bytecode.functions.push(Function::default());
+
+        let mut scope = Scope {
+            function: Function::default(),
+            locals: None,
+        };
+
+        // Subroutines and records can be used above their definition
+        for stmt in program {
+            if let Some(declaration) = self.declaration(stmt) {
+                scope.function.code.push(Instruction::Declare(declaration));
+            }
+        }
+
+        for stmt in program {
+            self.statement(&mut scope, stmt);
+            match &stmt.stmt_type {
+                StmtType::Expression(Expr {
+                    expr_type: ExprType::Call(..),
+                    ..
+                }) => scope.function.code.push(Instruction::SetLastReturned),
+                StmtType::Expression(_) => {
+                    // The value left by `statement` is kept instead of popped
+                    scope.function.code.pop();
+                    scope.function.code.push(Instruction::SetLast);
+                }
+                _ => scope.function.code.push(Instruction::ClearLast),
+            }
+        }
+        scope.function.code.push(Instruction::Halt);
+
+        self.bytecode.functions[0] = scope.function;
+        self.bytecode
+    }
+
+    fn declaration(&mut self, stmt: &Stmt) -> Option<usize> {
+        let key = match &stmt.stmt_type {
+            StmtType::Subroutine(subroutine) => Rc::as_ptr(subroutine) as usize,
+            StmtType::Record(record) => Rc::as_ptr(record) as usize,
+            _ => return None,
+        };
+        if let Some(&declaration) = self.declarations.get(&key) {
+            return Some(declaration);
+        }
+
+        let declaration = match &stmt.stmt_type {
+            StmtType::Subroutine(subroutine) => {
+                let callee = self.callee(&subroutine.name.name);
+                let function = self.function(subroutine);
+                Declaration::Subroutine(callee, function)
+            }
+            StmtType::Record(record) => {
+                let callee = self.callee(&record.name.name);
+                self.bytecode.records.push(record.clone());
+                Declaration::Record(callee, self.bytecode.records.len() - 1)
+            }
+            _ => unreachable!(),
+        };
+
+        self.bytecode.declarations.push(declaration);
+        let index = self.bytecode.declarations.len() - 1;
+        self.declarations.insert(key, index);
+        Some(index)
+    }
+
+    fn function(&mut self, subroutine: &Subroutine) -> usize {
+        let index = self.bytecode.functions.len();
+        self.bytecode.functions.push(Function::default());
+
+        let mut locals = HashMap::new();
+        for parameter in &subroutine.parameters {
+            let slot = locals.len();
+            locals.entry(parameter.name.name.clone()).or_insert(slot);
+        }
+        assigned_names(&subroutine.body, &mut locals);
+
+        let mut scope = Scope {
+            function: Function {
+                arity: subroutine.parameters.len(),
+                parameters: subroutine.parameters.clone(),
+                returns: subroutine.returns.clone(),
+                local_count: locals.len(),
+                ..Function::default()
+            },
+            locals: Some(locals),
+        };
+        for stmt in &subroutine.body {
+            self.statement(&mut scope, stmt);
+        }
+        scope.function.code.push(Instruction::ReturnNone);
+
+        self.bytecode.functions[index] = scope.function;
+        index
+    }
+
+    fn global(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.globals.get(name) {
+            return index;
+        }
+        self.bytecode.globals.push(name.to_string());
+        let index = self.bytecode.globals.len() - 1;
+        self.globals.insert(name.to_string(), index);
+        index
+    }
+
+    fn callee(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.callees.get(name) {
+            return index;
+        }
+        self.bytecode.callees.push(name.to_string());
+        let index = self.bytecode.callees.len() - 1;
+        self.callees.insert(name.to_string(), index);
+        index
+    }
+
+    fn constant(&mut self, value: Value) -> usize {
+        self.bytecode.constants.push(value);
+        self.bytecode.constants.len() - 1
+    }
+
+    fn operator(&mut self, operator: &TokenType) -> usize {
+        match self.bytecode.operators.iter().position(|o| o == operator) {
+            Some(index) => index,
+            None => {
+                self.bytecode.operators.push(operator.clone());
+                self.bytecode.operators.len() - 1
+            }
+        }
+    }
+
+    fn field(&mut self, field: &Identifier) -> usize {
+        self.bytecode.fields.push(field.clone());
+        self.bytecode.fields.len() - 1
+    }
+
+    fn declare(&mut self, scope: &mut Scope, name: &Identifier, annotation: &Annotation) {
+        self.bytecode.annotations.push(annotation.clone());
+        let annotation = self.bytecode.annotations.len() - 1;
+        let global = self.global(&name.name);
+        let span = scope.span(name.span);
+        let instruction = match scope.slot(&name.name) {
+            Some(slot) => Instruction::DeclareLocal(slot, global, annotation, span),
+            None => Instruction::DeclareGlobal(global, annotation, span),
+        };
+        scope.emit(instruction);
+    }
+
+    fn store(&mut self, scope: &mut Scope, name: &Identifier, constant: bool) {
+        let global = self.global(&name.name);
+        let span = scope.span(name.span);
+        let instruction = match scope.slot(&name.name) {
+            Some(slot) if constant => Instruction::DefineLocalConstant(slot, global, span),
+            Some(slot) => Instruction::StoreLocal(slot, global, span),
+            None if constant => Instruction::DefineGlobalConstant(global, span),
+            None => Instruction::StoreGlobal(global, span),
+        };
+        scope.emit(instruction);
+    }
+
+    fn statement(&mut self, scope: &mut Scope, stmt: &Stmt) {
+        if self.trace {
+            let span = scope.span(stmt.span);
+            scope.emit(Instruction::Trace(span));
+        }
+
+        match &stmt.stmt_type {
+            StmtType::Expression(Expr {
+                expr_type: ExprType::Call(name, arguments),
+                ..
+            }) => self.call(scope, name, arguments),
+            StmtType::Expression(expr) => {
+                self.expression(scope, expr);
+                scope.emit(Instruction::Pop);
+            }
+            StmtType::Assign(name, expr) => {
+                self.expression(scope, expr);
+                self.store(scope, name, false);
+            }
+            StmtType::AssignIndex(array, index, expr) => {
+                self.place(scope, array);
+                self.expression(scope, index);
+                let index_span = scope.span(index.span);
+                scope.emit(Instruction::ExpectInt(index_span));
+                self.expression(scope, expr);
+                let array_span = scope.span(array.span);
+                scope.emit(Instruction::StoreIndex(array_span, index_span));
+            }
+            StmtType::AssignField(record, field, expr) => {
+                self.expression(scope, record);
+                let record_span = scope.span(record.span);
+                scope.emit(Instruction::ExpectRecord(record_span));
+                self.expression(scope, expr);
+                let field = self.field(field);
+                scope.emit(Instruction::StoreField(field));
+            }
+            StmtType::Declare(name, annotation, expr) => {
+                if let Some(expr) = expr {
+                    self.expression(scope, expr);
+                }
+                self.declare(scope, name, annotation);
+                if expr.is_some() {
+                    self.store(scope, name, false);
+                }
+            }
+            StmtType::Constant(name, annotation, expr) => {
+                self.expression(scope, expr);
+                if let Some(annotation) = annotation {
+                    self.declare(scope, name, annotation);
+                }
+                self.store(scope, name, true);
+            }
+            StmtType::Output(exprs) => {
+                for expr in exprs {
+                    self.expression(scope, expr);
+                    scope.emit(Instruction::Stringify);
+                }
+                scope.emit(Instruction::Output(exprs.len()));
+            }
+            StmtType::If(condition, then_branch, else_branch) => {
+                self.expression(scope, condition);
+                let condition_span = scope.span(condition.span);
+                let jump_to_else = scope.emit(Instruction::JumpIfFalse(0, condition_span));
+                self.block(scope, then_branch);
+                let jump_to_end = scope.emit(Instruction::Jump(0));
+                scope.patch(jump_to_else);
+                self.block(scope, else_branch);
+                scope.patch(jump_to_end);
+            }
+            StmtType::For(name, start, end, step, body) => {
+                self.expression(scope, start);
+                let start_span = scope.span(start.span);
+                scope.emit(Instruction::ExpectInt(start_span));
+                self.expression(scope, end);
+                let end_span = scope.span(end.span);
+                scope.emit(Instruction::ExpectInt(end_span));
+                match step {
+                    Some(step) => {
+                        self.expression(scope, step);
+                        let step_span = scope.span(step.span);
+                        scope.emit(Instruction::ExpectInt(step_span));
+                        scope.emit(Instruction::CheckStep(step_span));
+                    }
+                    None => {
+                        let one = self.constant(Value::Int(1));
+                        scope.emit(Instruction::Constant(one));
+                    }
+                }
+
+                let start = scope.function.code.len();
+                let exit = scope.emit(Instruction::ForTest(0));
+                scope.emit(Instruction::ForCounter);
+                self.store(scope, name, false);
+                self.block(scope, body);
+                let overflow = scope.emit(Instruction::ForIncrement(0));
+                scope.emit(Instruction::Jump(start));
+                scope.patch(exit);
+                scope.patch(overflow);
+                scope.emit(Instruction::PopN(3));
+            }
+            StmtType::ForIn(name, iterable, body) => {
+                self.expression(scope, iterable);
+                let iterable_span = scope.span(iterable.span);
+                scope.emit(Instruction::IntoIterable(iterable_span));
+
+                let start = scope.function.code.len();
+                let exit = scope.emit(Instruction::ForInNext(0));
+                self.store(scope, name, false);
+                self.block(scope, body);
+                scope.emit(Instruction::Jump(start));
+                scope.patch(exit);
+                scope.emit(Instruction::PopN(2));
+            }
+            StmtType::While(condition, body) => {
+                let start = scope.function.code.len();
+                self.expression(scope, condition);
+                let condition_span = scope.span(condition.span);
+                let exit = scope.emit(Instruction::JumpIfFalse(0, condition_span));
+                self.block(scope, body);
+                scope.emit(Instruction::Jump(start));
+                scope.patch(exit);
+            }
+            StmtType::Repeat(body, condition) => {
+                let start = scope.function.code.len();
+                self.block(scope, body);
+                self.expression(scope, condition);
+                let condition_span = scope.span(condition.span);
+                scope.emit(Instruction::JumpIfFalse(start, condition_span));
+            }
+            StmtType::Subroutine(_) | StmtType::Record(_) => {
+                if let Some(declaration) = self.declaration(stmt) {
+                    scope.emit(Instruction::Declare(declaration));
+                }
+            }
+            StmtType::Return(Some(expr)) => {
+                self.expression(scope, expr);
+                scope.emit(Instruction::Return);
+            }
+            StmtType::Return(None) => {
+                scope.emit(Instruction::ReturnNone);
+            }
+        }
+    }
+
+    fn block(&mut self, scope: &mut Scope, block: &[Stmt]) {
+        for stmt in block {
+            self.statement(scope, stmt);
+        }
+    }
+
+    // Leaves the result in the VM's return register
+    fn call(&mut self, scope: &mut Scope, name: &Identifier, arguments: &[Expr]) {
+        let callee = self.callee(&name.name);
+        self.bytecode.calls.push(CallSite {
+            name: name.clone(),
+            callee,
+            arity: arguments.len(),
+            arguments: arguments.iter().map(|argument| argument.span).collect(),
+            builtin: builtins::lookup(&name.name),
+        });
+        let call = self.bytecode.calls.len() - 1;
+
+        scope.emit(Instruction::PrepareCall(call));
+        for argument in arguments {
+            self.expression(scope, argument);
+        }
+        scope.emit(Instruction::Call(call));
+    }
+
+    // Compiles the array of an element store, and pushes the type declared
+    // for it onto the stack of places
+    fn place(&mut self, scope: &mut Scope, expr: &Expr) {
+        match &expr.expr_type {
+            ExprType::Variable(name) => {
+                self.expression(scope, expr);
+                let global = self.global(&name.name);
+                let instruction = match scope.slot(&name.name) {
+                    Some(slot) => Instruction::LocalPlace(slot, global),
+                    None => Instruction::GlobalPlace(global),
+                };
+                scope.emit(instruction);
+            }
+            ExprType::Field(record, field) => {
+                self.expression(scope, record);
+                let field = self.field(field);
+                scope.emit(Instruction::FieldPlace(field));
+                let record_span = scope.span(record.span);
+                scope.emit(Instruction::Field(field, record_span));
+            }
+            ExprType::Index(array, index) => {
+                self.place(scope, array);
+                self.expression(scope, index);
+                let array_span = scope.span(array.span);
+                let index_span = scope.span(index.span);
+                scope.emit(Instruction::Index(array_span, index_span));
+                scope.emit(Instruction::ElementPlace);
+            }
+            _ => {
+                self.expression(scope, expr);
+                scope.emit(Instruction::NoPlace);
+            }
+        }
+    }
+
+    fn expression(&mut self, scope: &mut Scope, expr: &Expr) {
+        match &expr.expr_type {
+            ExprType::Literal(value) => {
+                let constant = self.constant(value.clone());
+                scope.emit(Instruction::Constant(constant));
+            }
+            ExprType::Variable(name) => {
+                let global = self.global(&name.name);
+                let span = scope.span(name.span);
+                let instruction = match scope.slot(&name.name) {
+                    Some(slot) => Instruction::LoadLocal(slot, global, span),
+                    None => Instruction::LoadGlobal(global, span),
+                };
+                scope.emit(instruction);
+            }
+            ExprType::Call(name, arguments) => {
+                self.call(scope, name, arguments);
+                let call = self.bytecode.calls.len() - 1;
+                let span = scope.span(expr.span);
+                scope.emit(Instruction::ExpectReturn(call, span));
+            }
+            ExprType::UserInput => {
+                let span = scope.span(expr.span);
+                scope.emit(Instruction::UserInput(span));
+            }
+            ExprType::Array(elements) => {
+                for element in elements {
+                    self.expression(scope, element);
+                }
+                scope.emit(Instruction::Array(elements.len()));
+            }
+            ExprType::Index(array, index) => {
+                self.expression(scope, array);
+                self.expression(scope, index);
+                let array_span = scope.span(array.span);
+                let index_span = scope.span(index.span);
+                scope.emit(Instruction::Index(array_span, index_span));
+            }
+            ExprType::Field(record, field) => {
+                self.expression(scope, record);
+                let field = self.field(field);
+                let record_span = scope.span(record.span);
+                scope.emit(Instruction::Field(field, record_span));
+            }
+            ExprType::Unary(operator, right) => {
+                self.expression(scope, right);
+                let operator = self.operator(&operator.token_type);
+                let span = scope.span(expr.span);
+                scope.emit(Instruction::Unary(operator, span));
+            }
+            ExprType::Binary(left, operator, right) => {
+                self.expression(scope, left);
+                self.expression(scope, right);
+                let operator = self.operator(&operator.token_type);
+                let right_span = scope.span(right.span);
+                let span = scope.span(expr.span);
+                scope.emit(Instruction::Binary(operator, right_span, span));
+            }
+            ExprType::Logical(left, operator, right) => {
+                self.expression(scope, left);
+                let is_and = operator.token_type == TokenType::LogicalAnd;
+                let left_span = scope.span(left.span);
+                let end = scope.emit(Instruction::ShortCircuit(is_and, 0, left_span));
+                self.expression(scope, right);
+                let right_span = scope.span(right.span);
+                scope.emit(Instruction::ExpectLogical(right_span));
+                scope.patch(end);
+            }
+        }
+    }
+}
+
+impl Scope {
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.function.code.push(instruction);
+        self.function.code.len() - 1
+    }
+
+    fn span(&mut self, span: Span) -> usize {
+        self.function.spans.push(span);
+        self.function.spans.len() - 1
+    }
+
+    fn slot(&self, name: &str) -> Option<usize> {
+        self.locals.as_ref()?.get(name).copied()
+    }
+
+    // Points the jump at `index` to the next instruction
+    fn patch(&mut self, index: usize) {
+        let target = self.function.code.len();
+        match &mut self.function.code[index] {
+            Instruction::Jump(jump)
+            | Instruction::JumpIfFalse(jump, _)
+            | Instruction::ShortCircuit(_, jump, _)
+            | Instruction::ForTest(jump)
+            | Instruction::ForIncrement(jump)
+            | Instruction::ForInNext(jump) => *jump = target,
+            instruction => unreachable!("cannot patch {:?}", instruction),
+        }
+    }
+}
+
+// Every name a subroutine assigns to lives in a local slot
+fn assigned_names(block: &[Stmt], locals: &mut HashMap<String, usize>) {
+    for stmt in block {
+        match &stmt.stmt_type {
+            StmtType::Assign(name, _)
+            | StmtType::Declare(name, ..)
+            | StmtType::Constant(name, ..)
+            | StmtType::For(name, ..)
+            | StmtType::ForIn(name, ..) => {
+                let slot = locals.len();
+                locals.entry(name.name.clone()).or_insert(slot);
+            }
+            _ => {}
+        }
+
+        match &stmt.stmt_type {
+            StmtType::If(_, then_branch, else_branch) => {
+                assigned_names(then_branch, locals);
+                assigned_names(else_branch, locals);
+            }
+            StmtType::For(.., body)
+            | StmtType::ForIn(.., body)
+            | StmtType::While(_, body)
+            | StmtType::Repeat(body, _) => assigned_names(body, locals),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,596 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{parser::RecordDefinition, Program, Record, Span, Value};
+
+use super::{
+    binary,
+    binding::{check_constant, lookup, Binding},
+    check_types_exist, conform, expect_bool, expect_int, expect_record, field_annotation,
+    load_field, load_index,
+    random::Rng,
+    store_field, store_index, unary, Error, ErrorType, InputSource, OutputSink, Place, Result,
+};
+
+mod chunk;
+mod compiler;
+
+use chunk::{Bytecode, Declaration, Instruction};
+use compiler::Compiler;
+
+// Where to resume the caller, and what to discard when the call returns
+struct Frame {
+    call: usize,
+    function: usize,
+    ip: usize,
+    locals_base: usize,
+    stack_base: usize,
+    callee_locals_base: usize,
+}
+
+/// Runs a program compiled to bytecode. Behaves exactly like the tree-walking
+/// [`Interpreter`](super::Interpreter), including the errors it reports.
+pub struct Vm<'a> {
+    output: &'a mut dyn OutputSink,
+    input: &'a mut dyn InputSource,
+    rng: Rng,
+    max_call_depth: usize,
+    trace: Option<Vec<Span>>,
+    globals: Vec<Option<Binding>>,
+    locals: Vec<Option<Binding>>,
+    stack: Vec<Value>,
+    places: Vec<Option<Place>>,
+    frames: Vec<Frame>,
+    subroutines: Vec<Option<usize>>,
+    records: Vec<Option<usize>>,
+    returned: Option<Value>,
+    last: Option<Value>,
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(
+        output: &'a mut dyn OutputSink,
+        input: &'a mut dyn InputSource,
+        seed: Option<u64>,
+        max_call_depth: usize,
+        trace: bool,
+    ) -> Self {
+        Self {
+            output,
+            input,
+            rng: Rng::new(seed),
+            max_call_depth,
+            trace: if trace { Some(Vec::new()) } else { None },
+            globals: Vec::new(),
+            locals: Vec::new(),
+            stack: Vec::new(),
+            places: Vec::new(),
+            frames: Vec::new(),
+            subroutines: Vec::new(),
+            records: Vec::new(),
+            returned: None,
+            last: None,
+        }
+    }
+
+    pub fn trace(&self) -> Option<&[Span]> {
+        self.trace.as_deref()
+    }
+
+    pub fn execute(&mut self, program: &Program) -> crate::Result<Option<Value>> {
+        check_types_exist(program, |_| false)?;
+        let bytecode = Compiler::new(self.trace.is_some()).compile(&program.statements);
+
+        self.globals = vec![None; bytecode.globals.len()];
+        self.subroutines = vec![None; bytecode.callees.len()];
+        self.records = vec![None; bytecode.callees.len()];
+
+        Ok(self.run(&bytecode)?)
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("stack underflow")
+    }
+
+    fn peek(&self, distance: usize) -> &Value {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+
+    fn run(&mut self, bytecode: &Bytecode) -> Result<Option<Value>> {
+        let mut current = 0;
+        let mut function = &bytecode.functions[current];
+        let mut ip = 0;
+        let mut locals_base = 0;
+
+        loop {
+            let instruction = function.code[ip];
+            ip += 1;
+
+            match instruction {
+                Instruction::Constant(index) => self.stack.push(bytecode.constants[index].clone()),
+                Instruction::Pop => {
+                    self.pop();
+                }
+                Instruction::PopN(count) => {
+                    self.stack.truncate(self.stack.len() - count);
+                }
+                Instruction::LoadGlobal(global, span) => {
+                    let value = self.load_global(bytecode, global, function.spans[span])?;
+                    self.stack.push(value);
+                }
+                Instruction::StoreGlobal(global, span) => {
+                    let value = self.pop();
+                    self.store_global(bytecode, global, value, false, function.spans[span])?;
+                }
+                Instruction::DefineGlobalConstant(global, span) => {
+                    let value = self.pop();
+                    self.store_global(bytecode, global, value, true, function.spans[span])?;
+                }
+                Instruction::LoadLocal(slot, global, span) => {
+                    let value = match &self.locals[locals_base + slot] {
+                        Some(binding) => lookup(
+                            Some(binding),
+                            &bytecode.globals[global],
+                            function.spans[span],
+                        )?,
+                        None => self.load_global(bytecode, global, function.spans[span])?,
+                    };
+                    self.stack.push(value);
+                }
+                Instruction::StoreLocal(slot, global, span) => {
+                    let value = self.pop();
+                    let span = function.spans[span];
+                    self.store_local(bytecode, locals_base + slot, global, value, false, span)?;
+                }
+                Instruction::DefineLocalConstant(slot, global, span) => {
+                    let value = self.pop();
+                    let span = function.spans[span];
+                    self.store_local(bytecode, locals_base + slot, global, value, true, span)?;
+                }
+                Instruction::DeclareGlobal(global, annotation, span) => {
+                    let span = function.spans[span];
+                    let name = &bytecode.globals[global];
+                    check_constant(None, self.globals[global].as_ref(), name, span)?;
+                    let annotation = &bytecode.annotations[annotation];
+                    self.globals[global] =
+                        Some(Binding::declare(name, annotation, None, false, span)?);
+                }
+                Instruction::DeclareLocal(slot, global, annotation, span) => {
+                    let span = function.spans[span];
+                    let name = &bytecode.globals[global];
+                    check_constant(
+                        self.locals[locals_base + slot].as_ref(),
+                        self.globals[global].as_ref(),
+                        name,
+                        span,
+                    )?;
+                    let annotation = &bytecode.annotations[annotation];
+                    self.locals[locals_base + slot] =
+                        Some(Binding::declare(name, annotation, None, false, span)?);
+                }
+                Instruction::UserInput(span) => match self.input.input() {
+                    Some(line) => self.stack.push(Value::String(line)),
+                    None => {
+                        return Err(Error::new(ErrorType::InputExhausted, function.spans[span]))
+                    }
+                },
+                Instruction::Stringify => {
+                    let value = self.pop();
+                    self.stack.push(Value::String(value.to_string()));
+                }
+                Instruction::Output(count) => {
+                    let mut line = String::new();
+                    for value in self.stack.drain(self.stack.len() - count..) {
+                        line += &value.to_string();
+                    }
+                    self.output.output(line);
+                }
+                Instruction::Array(count) => {
+                    let elements = self.stack.split_off(self.stack.len() - count);
+                    self.stack
+                        .push(Value::Array(Rc::new(RefCell::new(elements))));
+                }
+                Instruction::Index(array_span, index_span) => {
+                    let index = expect_int(self.pop(), function.spans[index_span])?;
+                    let array = self.pop();
+                    let value = load_index(
+                        array,
+                        index,
+                        function.spans[array_span],
+                        function.spans[index_span],
+                    )?;
+                    self.stack.push(value);
+                }
+                Instruction::StoreIndex(array_span, index_span) => {
+                    let value = self.pop();
+                    let index = expect_int(self.pop(), function.spans[index_span])?;
+                    let array = self.pop();
+                    let element = self
+                        .places
+                        .pop()
+                        .expect("a place is pushed for every element store")
+                        .and_then(Place::element);
+                    store_index(
+                        array,
+                        index,
+                        value,
+                        element,
+                        function.spans[array_span],
+                        function.spans[index_span],
+                    )?;
+                }
+                Instruction::Field(field, record_span) => {
+                    let record = expect_record(self.pop(), function.spans[record_span])?;
+                    let value = load_field(&record.borrow(), &bytecode.fields[field])?;
+                    self.stack.push(value);
+                }
+                Instruction::StoreField(field) => {
+                    let value = self.pop();
+                    let record = match self.pop() {
+                        Value::Record(record) => record,
+                        _ => unreachable!("ExpectRecord runs before the value is evaluated"),
+                    };
+                    let field = &bytecode.fields[field];
+
+                    let definition = self.definition(bytecode, &record.borrow().name);
+                    store_field(&record, field, value, definition)?;
+                }
+                Instruction::LocalPlace(slot, global) => {
+                    let binding = self.locals[locals_base + slot]
+                        .as_ref()
+                        .or(self.globals[global].as_ref());
+                    let annotation = binding.and_then(Binding::annotation);
+                    self.places
+                        .push(Place::new(&bytecode.globals[global], annotation));
+                }
+                Instruction::GlobalPlace(global) => {
+                    let annotation = self.globals[global].as_ref().and_then(Binding::annotation);
+                    self.places
+                        .push(Place::new(&bytecode.globals[global], annotation));
+                }
+                Instruction::FieldPlace(field) => {
+                    let field = &bytecode.fields[field];
+                    let place = match self.peek(0) {
+                        Value::Record(record) => {
+                            let definition = self.definition(bytecode, &record.borrow().name);
+                            Place::new(&field.name, field_annotation(definition, field))
+                        }
+                        _ => None,
+                    };
+                    self.places.push(place);
+                }
+                Instruction::ElementPlace => {
+                    let place = self.places.pop().expect("ElementPlace follows a place");
+                    self.places.push(place.and_then(Place::element));
+                }
+                Instruction::NoPlace => self.places.push(None),
+                Instruction::ExpectInt(span) => {
+                    if !matches!(self.peek(0), Value::Int(_)) {
+                        expect_int(self.pop(), function.spans[span])?;
+                    }
+                }
+                Instruction::ExpectRecord(span) => {
+                    if !matches!(self.peek(0), Value::Record(_)) {
+                        expect_record(self.pop(), function.spans[span])?;
+                    }
+                }
+                Instruction::Unary(operator, span) => {
+                    let right_value = self.pop();
+                    let value = unary(
+                        &bytecode.operators[operator],
+                        right_value,
+                        function.spans[span],
+                    )?;
+                    self.stack.push(value);
+                }
+                Instruction::Binary(operator, right_span, span) => {
+                    let right_value = self.pop();
+                    let left_value = self.pop();
+                    let value = binary(
+                        left_value,
+                        &bytecode.operators[operator],
+                        right_value,
+                        function.spans[right_span],
+                        function.spans[span],
+                    )?;
+                    self.stack.push(value);
+                }
+                Instruction::ShortCircuit(is_and, target, left_span) => {
+                    let left_value = match self.peek(0) {
+                        Value::Bool(value) => *value,
+                        _ => {
+                            return Err(Error::new(
+                                ErrorType::MismatchedType,
+                                function.spans[left_span],
+                            ))
+                        }
+                    };
+                    if left_value != is_and {
+                        ip = target;
+                    } else {
+                        self.pop();
+                    }
+                }
+                Instruction::ExpectLogical(span) => {
+                    if !matches!(self.peek(0), Value::Bool(_)) {
+                        return Err(Error::new(ErrorType::MismatchedType, function.spans[span]));
+                    }
+                }
+                Instruction::Jump(target) => ip = target,
+                Instruction::JumpIfFalse(target, span) => {
+                    if !expect_bool(self.pop(), function.spans[span])? {
+                        ip = target;
+                    }
+                }
+                Instruction::CheckStep(span) => {
+                    if let Value::Int(0) = self.peek(0) {
+                        return Err(Error::new(ErrorType::ZeroStep, function.spans[span]));
+                    }
+                }
+                Instruction::ForTest(exit) => {
+                    let (counter, end, step) = self.for_state();
+                    if !((step > 0 && counter <= end) || (step < 0 && counter >= end)) {
+                        ip = exit;
+                    }
+                }
+                Instruction::ForCounter => {
+                    let (counter, _, _) = self.for_state();
+                    self.stack.push(Value::Int(counter));
+                }
+                Instruction::ForIncrement(exit) => {
+                    let (counter, _, step) = self.for_state();
+                    match counter.checked_add(step) {
+                        Some(counter) => {
+                            let index = self.stack.len() - 3;
+                            self.stack[index] = Value::Int(counter);
+                        }
+                        None => ip = exit,
+                    }
+                }
+                Instruction::IntoIterable(span) => {
+                    let items: Vec<Value> = match self.pop() {
+                        Value::String(string) => string.chars().map(Value::Char).collect(),
+                        Value::Array(elements) => elements.borrow().clone(),
+                        value => {
+                            return Err(Error::new(
+                                ErrorType::NotIterable(value.type_name()),
+                                function.spans[span],
+                            ))
+                        }
+                    };
+                    self.stack.push(Value::Array(Rc::new(RefCell::new(items))));
+                    self.stack.push(Value::Int(0));
+                }
+                Instruction::ForInNext(exit) => {
+                    let next = match (self.peek(1), self.peek(0)) {
+                        (Value::Array(items), Value::Int(index)) => {
+                            items.borrow().get(*index as usize).cloned()
+                        }
+                        _ => unreachable!("IntoIterable pushes the items and an index"),
+                    };
+                    match next {
+                        Some(item) => {
+                            if let Some(Value::Int(index)) = self.stack.last_mut() {
+                                *index += 1;
+                            }
+                            self.stack.push(item);
+                        }
+                        None => ip = exit,
+                    }
+                }
+                Instruction::Declare(declaration) => match bytecode.declarations[declaration] {
+                    Declaration::Subroutine(callee, function) => {
+                        self.subroutines[callee] = Some(function)
+                    }
+                    Declaration::Record(callee, record) => self.records[callee] = Some(record),
+                },
+                Instruction::PrepareCall(call) => {
+                    let site = &bytecode.calls[call];
+                    if let Some(callee) = self.subroutines[site.callee] {
+                        let arity = bytecode.functions[callee].arity;
+                        if site.arity != arity {
+                            return Err(Error::new(
+                                ErrorType::ArityMismatch(site.name.name.clone(), arity, site.arity),
+                                site.name.span,
+                            ));
+                        }
+                        if self.frames.len() >= self.max_call_depth {
+                            return Err(Error::new(
+                                ErrorType::StackOverflow(site.name.name.clone()),
+                                site.name.span,
+                            ));
+                        }
+                    } else if let Some(record) = self.records[site.callee] {
+                        let arity = bytecode.records[record].fields.len();
+                        if site.arity != arity {
+                            return Err(Error::new(
+                                ErrorType::ArityMismatch(site.name.name.clone(), arity, site.arity),
+                                site.name.span,
+                            ));
+                        }
+                    } else if site.builtin.is_none() {
+                        return Err(Error::new(
+                            ErrorType::UndefinedSubroutine(site.name.name.clone()),
+                            site.name.span,
+                        ));
+                    }
+                }
+                Instruction::Call(call) => {
+                    let site = &bytecode.calls[call];
+                    let arguments = self.stack.split_off(self.stack.len() - site.arity);
+
+                    if let Some(callee) = self.subroutines[site.callee] {
+                        let parameters = &bytecode.functions[callee].parameters;
+                        let arguments = arguments
+                            .into_iter()
+                            .zip(parameters)
+                            .zip(&site.arguments)
+                            .map(|((value, parameter), span)| match &parameter.annotation {
+                                Some(annotation) => {
+                                    conform(value, annotation, &parameter.name.name, *span)
+                                }
+                                None => Ok(value),
+                            })
+                            .collect::<Result<Vec<_>>>()?;
+
+                        self.frames.push(Frame {
+                            call,
+                            function: current,
+                            ip,
+                            locals_base,
+                            stack_base: self.stack.len(),
+                            callee_locals_base: self.locals.len(),
+                        });
+
+                        current = callee;
+                        function = &bytecode.functions[current];
+                        ip = 0;
+                        locals_base = self.locals.len();
+                        self.locals.resize(locals_base + function.local_count, None);
+                        for (slot, (value, parameter)) in
+                            arguments.into_iter().zip(&function.parameters).enumerate()
+                        {
+                            self.locals[locals_base + slot] =
+                                Some(Binding::parameter(value, parameter.annotation.clone()));
+                        }
+                    } else if let Some(record) = self.records[site.callee] {
+                        let record = &bytecode.records[record];
+                        let fields = record
+                            .fields
+                            .iter()
+                            .zip(arguments)
+                            .zip(&site.arguments)
+                            .map(|((field, value), span)| {
+                                let value =
+                                    conform(value, &field.annotation, &field.name.name, *span)?;
+                                Ok((field.name.name.clone(), value))
+                            })
+                            .collect::<Result<_>>()?;
+                        self.returned = Some(Value::Record(Rc::new(RefCell::new(Record {
+                            name: record.name.name.clone(),
+                            fields,
+                        }))));
+                    } else if let Some(builtin) = site.builtin {
+                        self.returned = Some(builtin.call(&mut self.rng, arguments, &site.name)?);
+                    }
+                }
+                Instruction::ExpectReturn(call, span) => match self.returned.take() {
+                    Some(value) => self.stack.push(value),
+                    None => {
+                        return Err(Error::new(
+                            ErrorType::NoReturnValue(bytecode.calls[call].name.name.clone()),
+                            function.spans[span],
+                        ))
+                    }
+                },
+                Instruction::Return | Instruction::ReturnNone => {
+                    self.returned = match instruction {
+                        Instruction::Return => Some(self.pop()),
+                        _ => None,
+                    };
+
+                    let frame = self.frames.pop().expect("RETURN outside a subroutine");
+                    self.stack.truncate(frame.stack_base);
+                    self.locals.truncate(frame.callee_locals_base);
+
+                    if let Some(returns) = &function.returns {
+                        if let Some(value) = self.returned.take() {
+                            let name = &bytecode.calls[frame.call].name;
+                            self.returned = Some(conform(value, returns, &name.name, name.span)?);
+                        }
+                    }
+
+                    current = frame.function;
+                    function = &bytecode.functions[current];
+                    ip = frame.ip;
+                    locals_base = frame.locals_base;
+                }
+                Instruction::SetLast => self.last = Some(self.pop()),
+                Instruction::SetLastReturned => self.last = self.returned.take(),
+                Instruction::ClearLast => self.last = None,
+                Instruction::Trace(span) => {
+                    if let Some(trace) = &mut self.trace {
+                        trace.push(function.spans[span]);
+                    }
+                }
+                Instruction::Halt => return Ok(self.last.take()),
+            }
+        }
+    }
+
+    // The definition of the record type called `name`, as currently declared
+    fn definition<'b>(
+        &self,
+        bytecode: &'b Bytecode,
+        name: &str,
+    ) -> Option<&'b Rc<RecordDefinition>> {
+        bytecode
+            .callees
+            .iter()
+            .position(|callee| callee == name)
+            .and_then(|callee| self.records[callee])
+            .map(|record| &bytecode.records[record])
+    }
+
+    fn for_state(&self) -> (i64, i64, i64) {
+        match (self.peek(2), self.peek(1), self.peek(0)) {
+            (Value::Int(counter), Value::Int(end), Value::Int(step)) => (*counter, *end, *step),
+            _ => unreachable!("FOR loops keep three integers on the stack"),
+        }
+    }
+
+    fn load_global(&self, bytecode: &Bytecode, global: usize, span: Span) -> Result<Value> {
+        lookup(
+            self.globals[global].as_ref(),
+            &bytecode.globals[global],
+            span,
+        )
+    }
+
+    fn store_global(
+        &mut self,
+        bytecode: &Bytecode,
+        global: usize,
+        value: Value,
+        constant: bool,
+        span: Span,
+    ) -> Result<()> {
+        let name = &bytecode.globals[global];
+        check_constant(None, self.globals[global].as_ref(), name, span)?;
+        Self::bind(&mut self.globals[global], name, value, constant, span)
+    }
+
+    fn store_local(
+        &mut self,
+        bytecode: &Bytecode,
+        slot: usize,
+        global: usize,
+        value: Value,
+        constant: bool,
+        span: Span,
+    ) -> Result<()> {
+        let name = &bytecode.globals[global];
+        check_constant(
+            self.locals[slot].as_ref(),
+            self.globals[global].as_ref(),
+            name,
+            span,
+        )?;
+        Self::bind(&mut self.locals[slot], name, value, constant, span)
+    }
+
+    fn bind(
+        binding: &mut Option<Binding>,
+        name: &str,
+        value: Value,
+        constant: bool,
+        span: Span,
+    ) -> Result<()> {
+        match binding {
+            Some(binding) => binding.assign(name, value, constant, span),
+            None => {
+                *binding = Some(Binding::new(value, constant));
+                Ok(())
+            }
+        }
+    }
+}
@@ -1,14 +1,79 @@
+mod checker;
+mod diagnostic;
 mod error;
 mod interpreter;
 mod parser;
 mod scanner;
 
+use std::{cell::RefCell, fmt, ops::Range, rc::Rc};
+
+pub use diagnostic::*;
 pub use error::*;
+pub use interpreter::{
+    InputSource, Interpreter, OutputSink, StdinSource, StdoutSink, DEFAULT_MAX_CALL_DEPTH,
+};
+pub use parser::Stmt as Statement;
+pub use scanner::{Token, TokenType};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Position {
-    line: usize,
-    column: usize,
+    line: u32,
+    column: u32,
+    offset: u32,
+}
+
+impl Position {
+    pub fn line(&self) -> usize {
+        self.line as usize
+    }
+
+    pub fn column(&self) -> usize {
+        self.column as usize
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Span {
+    start: Position,
+    end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+
+    pub fn start(&self) -> Position {
+        self.start
+    }
+
+    pub fn end(&self) -> Position {
+        self.end
+    }
+
+    /// Byte offsets of the span within the source.
+    pub fn range(&self) -> Range<usize> {
+        self.start.offset()..self.end.offset()
+    }
+
+    /// Returns a span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        let start = if other.start.offset < self.start.offset {
+            other.start
+        } else {
+            self.start
+        };
+        let end = if other.end.offset > self.end.offset {
+            other.end
+        } else {
+            self.end
+        };
+        Span { start, end }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -17,33 +82,330 @@ pub enum Value {
     Float(f64),
     Bool(bool),
     String(String),
+    /// A single character. Character literals are written in double quotes,
+    /// e.g. `"a"`, while single quotes always make a String, even around one
+    /// character.
+    Char(char),
+    Array(Rc<RefCell<Vec<Value>>>),
+    Record(Rc<RefCell<Record>>),
 }
 
-pub fn run(contents: String) -> Result<Value> {
-    let mut scanner = scanner::Scanner::new(contents);
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub fields: Vec<(String, Value)>,
+}
+
+impl Value {
+    pub fn type_name(&self) -> String {
+        match self {
+            Value::Int(_) => String::from("Integer"),
+            Value::Float(_) => String::from("Real"),
+            Value::Bool(_) => String::from("Boolean"),
+            Value::String(_) => String::from("String"),
+            Value::Char(_) => String::from("Char"),
+            Value::Array(_) => String::from("Array"),
+            Value::Record(record) => record.borrow().name.clone(),
+        }
+    }
+}
+
+impl Value {
+    // `open` holds the arrays and records being written further up, so a
+    // value that contains itself is cut short instead of recursing forever
+    fn write(&self, f: &mut fmt::Formatter<'_>, open: &mut Vec<*const ()>) -> fmt::Result {
+        match self {
+            Value::Array(elements) => {
+                let pointer = Rc::as_ptr(elements) as *const ();
+                if open.contains(&pointer) {
+                    return write!(f, "[...]");
+                }
+
+                open.push(pointer);
+                write!(f, "[")?;
+                for (i, element) in elements.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.write(f, open)?;
+                }
+                open.pop();
+                write!(f, "]")
+            }
+            Value::Record(record) => {
+                let pointer = Rc::as_ptr(record) as *const ();
+                let record = record.borrow();
+                if open.contains(&pointer) {
+                    return write!(f, "{}(...)", record.name);
+                }
+
+                open.push(pointer);
+                write!(f, "{}(", record.name)?;
+                for (i, (name, value)) in record.fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", name)?;
+                    value.write(f, open)?;
+                }
+                open.pop();
+                write!(f, ")")
+            }
+            value => write!(f, "{}", value),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            // Whole numbers keep their ".0" so the output reads back as a Real literal
+            Value::Float(value) if value.is_finite() && value.fract() == 0.0 => {
+                write!(f, "{:.1}", value)
+            }
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Bool(true) => write!(f, "True"),
+            Value::Bool(false) => write!(f, "False"),
+            Value::String(value) => write!(f, "{}", value),
+            Value::Char(value) => write!(f, "{}", value),
+            Value::Array(_) | Value::Record(_) => self.write(f, &mut Vec::new()),
+        }
+    }
+}
+
+pub fn scan(contents: String) -> Result<Vec<Token>> {
+    scan_from(contents, 0)
+}
+
+fn scan_from(contents: String, start: usize) -> Result<Vec<Token>> {
+    let mut scanner = scanner::Scanner::starting_at(contents, start);
 
     let mut tokens = Vec::new();
 
     let mut exit = false;
     while !exit {
         let token = scanner.scan_token()?;
-        if token.token_type == scanner::TokenType::Eof {
+        if token.token_type == TokenType::Eof {
             exit = true;
         }
         tokens.push(token);
     }
 
-    println!("{:?}", tokens);
+    Ok(tokens)
+}
+
+/// A parsed program, ready to be run by an [`Interpreter`].
+#[derive(Debug)]
+pub struct Program {
+    statements: Vec<parser::Stmt>,
+}
+
+impl Program {
+    pub fn statements(&self) -> &[Statement] {
+        &self.statements
+    }
+}
+
+pub fn parse(contents: String) -> Result<Program> {
+    parse_from(contents, 0)
+}
+
+/// Parses `contents[start..]` only. Spans still index into the whole of
+/// `contents`, while their lines and columns count from `start`, so a host
+/// can keep everything it has run in one growing source to render errors
+/// against.
+pub fn parse_from(contents: String, start: usize) -> Result<Program> {
+    let tokens = scan_from(contents, start)?;
 
     let mut parser = parser::Parser::new(tokens);
+    let statements = parser.parse_program()?;
+
+    Ok(Program { statements })
+}
+
+/// Reports every syntax error in `contents` instead of stopping at the first one.
+pub fn check(contents: String) -> Vec<Diagnostic> {
+    check_from(contents, 0)
+}
+
+/// [`check`] for the part of `contents` that [`parse_from`] would parse.
+pub fn check_from(contents: String, start: usize) -> Vec<Diagnostic> {
+    let tokens = match scan_from(contents, start) {
+        Ok(tokens) => tokens,
+        Err(error) => return vec![Diagnostic::from(&error)],
+    };
+
+    let mut parser = parser::Parser::new(tokens);
+    let (_, errors) = parser.parse_program_with_recovery();
+
+    errors.iter().map(Diagnostic::from).collect()
+}
+
+/// Reports every type error in `contents` that can be found without running
+/// it, or its syntax errors if it does not parse.
+pub fn check_types(contents: String) -> Vec<Diagnostic> {
+    let program = match parse(contents.clone()) {
+        Ok(program) => program,
+        Err(_) => return check(contents),
+    };
+
+    checker::check(&program.statements)
+        .iter()
+        .map(Diagnostic::from)
+        .collect()
+}
+
+/// How [`run`] executes a parsed program.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Backend {
+    /// Walks the syntax tree directly.
+    #[default]
+    TreeWalker,
+    /// Compiles to bytecode first and runs it on a stack machine, which is
+    /// faster for loop-heavy programs.
+    Bytecode,
+}
 
-    let expression = parser.parse()?;
+/// Controls how [`run`] executes and what it collects alongside the
+/// program's output.
+#[derive(Clone, Debug)]
+pub struct RunOptions {
+    pub dump_tokens: bool,
+    pub dump_ast: bool,
+    pub trace: bool,
+    pub seed: Option<u64>,
+    pub backend: Backend,
+    /// Rejects programs with type errors before any of their code runs.
+    pub check_types: bool,
+    /// How deeply subroutine calls may nest before the program stops with a
+    /// stack overflow error.
+    pub max_call_depth: usize,
+}
 
-    println!("{:?}", expression);
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            dump_tokens: false,
+            dump_ast: false,
+            trace: false,
+            seed: None,
+            backend: Backend::default(),
+            check_types: false,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+        }
+    }
+}
 
-    let value = interpreter::evaluate(expression)?;
+/// The result of a successful [`run`].
+///
+/// `tokens`, `ast` and `trace` are only filled in when requested through
+/// [`RunOptions`].
+#[derive(Debug, Default)]
+pub struct RunOutput {
+    pub output: Vec<String>,
+    pub value: Option<Value>,
+    pub tokens: Option<Vec<Token>>,
+    pub ast: Option<String>,
+    pub trace: Option<Vec<String>>,
+}
+
+// Forwards each line to the caller's sink while keeping a copy for `RunOutput`
+struct RecordingSink<'a> {
+    sink: &'a mut dyn OutputSink,
+    lines: Vec<String>,
+}
+
+impl OutputSink for RecordingSink<'_> {
+    fn output(&mut self, line: String) {
+        self.lines.push(line.clone());
+        self.sink.output(line);
+    }
+}
+
+pub fn run(
+    contents: String,
+    output: &mut dyn OutputSink,
+    input: &mut dyn InputSource,
+    options: &RunOptions,
+) -> Result<RunOutput> {
+    let source = if options.trace {
+        Some(contents.clone())
+    } else {
+        None
+    };
+
+    let tokens = scan(contents)?;
+    let dumped_tokens = if options.dump_tokens {
+        Some(tokens.clone())
+    } else {
+        None
+    };
+
+    let mut parser = parser::Parser::new(tokens);
+    let program = Program {
+        statements: parser.parse_program()?,
+    };
+    let ast = if options.dump_ast {
+        Some(format!("{:#?}", program.statements))
+    } else {
+        None
+    };
+
+    if options.check_types {
+        if let Some(error) = checker::check(&program.statements).into_iter().next() {
+            return Err(error.into());
+        }
+    }
+
+    let mut recorder = RecordingSink {
+        sink: output,
+        lines: Vec::new(),
+    };
+    let (value, spans) = match options.backend {
+        Backend::TreeWalker => {
+            let mut interpreter = Interpreter::new(&mut recorder, input)
+                .with_seed(options.seed)
+                .with_max_call_depth(options.max_call_depth);
+            if options.trace {
+                interpreter = interpreter.with_trace();
+            }
+            let value = interpreter.execute(&program)?;
+            (value, interpreter.trace().unwrap_or_default().to_vec())
+        }
+        Backend::Bytecode => {
+            let mut vm = interpreter::Vm::new(
+                &mut recorder,
+                input,
+                options.seed,
+                options.max_call_depth,
+                options.trace,
+            );
+            let value = vm.execute(&program)?;
+            (value, vm.trace().unwrap_or_default().to_vec())
+        }
+    };
 
-    println!("{:?}", value);
+    let trace = source.map(|source| {
+        spans
+            .iter()
+            .map(|span| {
+                let text = source[span.range()].lines().next().unwrap_or_default();
+                format!(
+                    "{}:{}: {}",
+                    span.start().line(),
+                    span.start().column(),
+                    text.trim()
+                )
+            })
+            .collect()
+    });
 
-    Ok(value)
+    Ok(RunOutput {
+        output: recorder.lines,
+        value,
+        tokens: dumped_tokens,
+        ast,
+        trace,
+    })
 }
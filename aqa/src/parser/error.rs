@@ -1,41 +1,83 @@
 use std::fmt;
 
-use crate::{scanner::Token, Position};
+use crate::{scanner::TokenType, Span};
 
 #[derive(Debug)]
 pub enum ErrorType {
-    UnexpectedToken(Token),
+    UnexpectedToken(TokenType),
     ExpectedRightParen,
+    ExpectedLineBreak(TokenType),
+    ExpectedIdentifier(TokenType),
+    Expected(TokenType, TokenType),
+    InvalidAssignmentTarget,
+    UnterminatedBlock(TokenType, TokenType),
+    MismatchedTerminator(TokenType, TokenType, TokenType),
+    NestedSubroutine,
+    ReturnOutsideSubroutine,
 }
 
 #[derive(Debug)]
 pub struct Error {
-    position: Position,
+    pub span: Span,
     pub error_type: ErrorType,
 }
 
 impl Error {
-    pub fn new(position: Position, error_type: ErrorType) -> Self {
-        Self {
-            position,
-            error_type,
-        }
+    pub fn new(span: Span, error_type: ErrorType) -> Self {
+        Self { span, error_type }
     }
 }
 
-impl fmt::Display for Error {
+impl fmt::Display for ErrorType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let error_message = match &self.error_type {
-            ErrorType::UnexpectedToken(token) => {
-                format!("Unexpected token: '{:?}'", token.token_type)
+        let message = match self {
+            ErrorType::UnexpectedToken(found) => format!("Unexpected {}", found.describe()),
+            ErrorType::ExpectedRightParen => String::from("Expected ')'"),
+            ErrorType::ExpectedLineBreak(found) => {
+                format!("Expected end of line, found {}", found.describe())
+            }
+            ErrorType::ExpectedIdentifier(found) => {
+                format!("Expected a name, found {}", found.describe())
+            }
+            ErrorType::Expected(expected, found) => {
+                format!(
+                    "Expected {}, found {}",
+                    expected.describe(),
+                    found.describe()
+                )
+            }
+            ErrorType::InvalidAssignmentTarget => String::from("Invalid assignment target"),
+            ErrorType::UnterminatedBlock(opener, terminator) => format!(
+                "{} is never closed, expected {}",
+                opener.describe(),
+                terminator.describe()
+            ),
+            ErrorType::MismatchedTerminator(opener, expected, found) => format!(
+                "{} cannot close {}, expected {}",
+                found.describe(),
+                opener.describe(),
+                expected.describe()
+            ),
+            ErrorType::NestedSubroutine => {
+                String::from("Subroutines cannot be declared inside other subroutines")
+            }
+            ErrorType::ReturnOutsideSubroutine => {
+                String::from("RETURN can only be used inside a subroutine")
             }
-            ErrorType::ExpectedRightParen => String::from("Expected Right Parenthesis"),
         };
 
+        write!(f, "{}", message)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "at line {}, column {}: {}",
-            self.position.line, self.position.column, error_message
+            self.span.start().line(),
+            self.span.start().column(),
+            self.error_type
         )
     }
 }
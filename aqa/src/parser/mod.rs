@@ -1,33 +1,248 @@
 use crate::{
     scanner::{Token, TokenType},
-    Value,
+    Span, Value,
 };
 
 mod error;
 
 pub use error::*;
 
-use std::mem;
+use std::{fmt, mem, rc::Rc};
+
+#[derive(Debug, Clone)]
+pub struct Identifier {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct Expr {
+    pub expr_type: ExprType,
+    pub span: Span,
+}
 
 #[derive(Debug)]
 pub enum ExprType {
     Literal(Value),
-    Unary(Token, Box<ExprType>),
-    Binary(Box<ExprType>, Token, Box<ExprType>),
+    Variable(Identifier),
+    Call(Identifier, Vec<Expr>),
+    UserInput,
+    Array(Vec<Expr>),
+    Index(Box<Expr>, Box<Expr>),
+    Field(Box<Expr>, Identifier),
+    Unary(Token, Box<Expr>),
+    Binary(Box<Expr>, Token, Box<Expr>),
+    Logical(Box<Expr>, Token, Box<Expr>),
+}
+
+#[derive(Debug)]
+pub struct Stmt {
+    pub stmt_type: StmtType,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum StmtType {
+    Expression(Expr),
+    Assign(Identifier, Expr),
+    AssignIndex(Expr, Expr, Expr),
+    AssignField(Expr, Identifier, Expr),
+    Declare(Identifier, Annotation, Option<Expr>),
+    Constant(Identifier, Option<Annotation>, Expr),
+    Output(Vec<Expr>),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    For(Identifier, Expr, Expr, Option<Expr>, Vec<Stmt>),
+    ForIn(Identifier, Expr, Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+    Repeat(Vec<Stmt>, Expr),
+    Subroutine(Rc<Subroutine>),
+    Record(Rc<RecordDefinition>),
+    Return(Option<Expr>),
+}
+
+impl Expr {
+    fn new(expr_type: ExprType, span: Span) -> Self {
+        Self { expr_type, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Annotation {
+    Integer,
+    Real,
+    Boolean,
+    String,
+    Char,
+    Array(Box<Annotation>),
+    Record(String),
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Annotation::Integer => write!(f, "Integer"),
+            Annotation::Real => write!(f, "Real"),
+            Annotation::Boolean => write!(f, "Boolean"),
+            Annotation::String => write!(f, "String"),
+            Annotation::Char => write!(f, "Char"),
+            Annotation::Array(element) => write!(f, "{}[]", element),
+            Annotation::Record(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: Identifier,
+    pub annotation: Option<Annotation>,
+}
+
+#[derive(Debug)]
+pub struct Subroutine {
+    pub name: Identifier,
+    pub parameters: Vec<Parameter>,
+    pub returns: Option<Annotation>,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug)]
+pub struct RecordField {
+    pub name: Identifier,
+    pub annotation: Annotation,
+}
+
+#[derive(Debug)]
+pub struct RecordDefinition {
+    pub name: Identifier,
+    pub fields: Vec<RecordField>,
+}
+
+impl Annotation {
+    /// The record type this annotation refers to, if any.
+    pub fn record(&self) -> Option<&str> {
+        match self {
+            Annotation::Array(element) => element.record(),
+            Annotation::Record(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// Finds every annotation in `block` naming a type that is neither built in
+/// nor a RECORD, returned with the name it annotates. Records declared in
+/// `block` are known, as are any `is_record` accepts, e.g. ones declared
+/// by an earlier program.
+pub fn unknown_types(
+    block: &[Stmt],
+    is_record: impl Fn(&str) -> bool,
+) -> Vec<(String, Identifier)> {
+    let mut records = Vec::new();
+    let mut annotated = Vec::new();
+    annotations(block, &mut records, &mut annotated);
+
+    annotated
+        .into_iter()
+        .filter_map(|(annotation, name)| {
+            let record = annotation.record()?;
+            if records.contains(&record) || is_record(record) {
+                None
+            } else {
+                Some((record.to_string(), name.clone()))
+            }
+        })
+        .collect()
+}
+
+fn annotations<'a>(
+    block: &'a [Stmt],
+    records: &mut Vec<&'a str>,
+    annotated: &mut Vec<(&'a Annotation, &'a Identifier)>,
+) {
+    for stmt in block {
+        match &stmt.stmt_type {
+            StmtType::Declare(name, annotation, _)
+            | StmtType::Constant(name, Some(annotation), _) => annotated.push((annotation, name)),
+            StmtType::If(_, then_branch, else_branch) => {
+                annotations(then_branch, records, annotated);
+                annotations(else_branch, records, annotated);
+            }
+            StmtType::For(.., body)
+            | StmtType::ForIn(.., body)
+            | StmtType::While(_, body)
+            | StmtType::Repeat(body, _) => annotations(body, records, annotated),
+            StmtType::Subroutine(subroutine) => {
+                for parameter in &subroutine.parameters {
+                    if let Some(annotation) = &parameter.annotation {
+                        annotated.push((annotation, &parameter.name));
+                    }
+                }
+                if let Some(returns) = &subroutine.returns {
+                    annotated.push((returns, &subroutine.name));
+                }
+                annotations(&subroutine.body, records, annotated);
+            }
+            StmtType::Record(record) => {
+                records.push(&record.name.name);
+                for field in &record.fields {
+                    annotated.push((&field.annotation, &field.name));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::large_enum_variant)] // only lives while a FOR header is parsed
+enum ForHeader {
+    Range(Identifier, Expr, Expr, Option<Expr>),
+    In(Identifier, Expr),
 }
 
 pub struct Parser {
     tokens: Vec<Token>,
     index: usize,
+    in_subroutine: bool,
+    errors: Vec<Error>,
+    open_blocks: Vec<Vec<TokenType>>,
+    left_terminator: bool,
+    // The open block that a terminator left on the line will close
+    closing: Option<usize>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, index: 0 }
+        Self {
+            tokens,
+            index: 0,
+            in_subroutine: false,
+            errors: Vec::new(),
+            open_blocks: Vec::new(),
+            left_terminator: false,
+            closing: None,
+        }
+    }
+
+    pub fn parse_program(&mut self) -> Result<Vec<Stmt>> {
+        let (statements, mut errors) = self.parse_program_with_recovery();
+        if errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(errors.remove(0))
+        }
     }
 
-    pub fn parse(&mut self) -> Result<ExprType> {
-        self.expression()
+    pub fn parse_program_with_recovery(&mut self) -> (Vec<Stmt>, Vec<Error>) {
+        let mut statements = Vec::new();
+
+        self.skip_line_breaks();
+        while !self.check(TokenType::Eof) {
+            if let Some(stmt) = self.declaration() {
+                statements.push(stmt);
+            }
+            self.skip_line_breaks();
+        }
+
+        (statements, mem::take(&mut self.errors))
     }
 
     fn peek(&mut self) -> Token {
@@ -62,23 +277,607 @@ impl Parser {
         }
     }
 
-    fn expression(&mut self) -> Result<ExprType> {
-        self.equality()
+    fn skip_line_breaks(&mut self) {
+        while self.match_token(TokenType::LineBreak) {}
+    }
+
+    fn end_of_statement(&mut self) -> Result<()> {
+        // A block closed by an enclosing block's terminator leaves that terminator on the line
+        if mem::take(&mut self.left_terminator) {
+            return Ok(());
+        }
+
+        if self.match_token(TokenType::LineBreak) || self.check(TokenType::Eof) {
+            Ok(())
+        } else {
+            let token = self.peek();
+            Err(Error::new(
+                token.span,
+                ErrorType::ExpectedLineBreak(token.token_type),
+            ))
+        }
+    }
+
+    // Skips the rest of a broken statement, stopping early at a terminator that can close an open block
+    fn synchronize(&mut self) {
+        while !self.check(TokenType::Eof) {
+            if self.match_token(TokenType::LineBreak) {
+                return;
+            }
+            if !self.open_blocks.is_empty() && Self::is_block_terminator(&self.peek().token_type) {
+                return;
+            }
+            self.advance();
+        }
+    }
+
+    fn identifier(&mut self) -> Result<Identifier> {
+        let token = self.peek();
+        if let TokenType::Identifier(name) = token.token_type {
+            self.advance();
+            Ok(Identifier {
+                name,
+                span: token.span,
+            })
+        } else {
+            Err(Error::new(
+                token.span,
+                ErrorType::ExpectedIdentifier(token.token_type.clone()),
+            ))
+        }
+    }
+
+    fn consume(&mut self, token_type: TokenType) -> Result<Token> {
+        if self.check(token_type.clone()) {
+            Ok(self.advance())
+        } else {
+            let token = self.peek();
+            Err(Error::new(
+                token.span,
+                ErrorType::Expected(token_type, token.token_type.clone()),
+            ))
+        }
+    }
+
+    // Parses a block header, recording any error and skipping to the end of the line so the body is still parsed
+    fn header<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Option<T> {
+        match parse(self) {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.errors.push(error);
+                while !self.check(TokenType::LineBreak) && !self.check(TokenType::Eof) {
+                    self.advance();
+                }
+                None
+            }
+        }
+    }
+
+    fn open_block(&mut self, terminators: &[TokenType]) {
+        self.open_blocks.push(terminators.to_vec());
+        if let Err(error) = self.end_of_statement() {
+            self.errors.push(error);
+            self.synchronize();
+        }
+        self.skip_line_breaks();
+    }
+
+    // Returns the token that ends the block, if the block ends here
+    fn close_block(&mut self, opener: &Token, terminators: &[TokenType]) -> Option<Token> {
+        let depth = self.open_blocks.len() - 1;
+
+        // Blocks inside the one a left terminator closes end quietly, as the
+        // mismatch has already been reported
+        if self.closing.is_some_and(|closing| closing < depth) {
+            self.left_terminator = true;
+            return Some(self.peek());
+        }
+
+        if terminators.iter().any(|t| self.check(t.clone())) {
+            self.closing = None;
+            return Some(self.advance());
+        }
+
+        let token = self.peek();
+        if Self::is_block_terminator(&token.token_type) {
+            self.errors.push(Error::new(
+                token.span,
+                ErrorType::MismatchedTerminator(
+                    opener.token_type.clone(),
+                    terminators[terminators.len() - 1].clone(),
+                    token.token_type.clone(),
+                ),
+            ));
+
+            // The terminator either closes an enclosing block whose inner
+            // terminators are missing, or is a typo for this block's own
+            // terminator. Whichever leaves the rest of the file balanced wins.
+            let enclosing = self.open_blocks[..depth]
+                .iter()
+                .rposition(|terminators| terminators.contains(&token.token_type));
+            let closes_enclosing = enclosing.is_some_and(|enclosing| {
+                let mut remaining = self.open_blocks[..enclosing].to_vec();
+                if token.token_type == TokenType::Else {
+                    remaining.push(vec![TokenType::EndIf]);
+                }
+                self.unbalanced_after(remaining)
+                    <= self.unbalanced_after(self.open_blocks[..depth].to_vec())
+            });
+
+            if closes_enclosing {
+                self.closing = enclosing;
+                self.left_terminator = true;
+            } else {
+                self.advance();
+            }
+            return Some(token);
+        }
+
+        if self.check(TokenType::Eof) {
+            self.errors.push(Error::new(
+                opener.span,
+                ErrorType::UnterminatedBlock(
+                    opener.token_type.clone(),
+                    terminators[terminators.len() - 1].clone(),
+                ),
+            ));
+            return Some(token);
+        }
+
+        None
+    }
+
+    fn block(&mut self, opener: &Token, terminators: &[TokenType]) -> (Vec<Stmt>, Token) {
+        let mut statements = Vec::new();
+
+        self.open_block(terminators);
+        let terminator = loop {
+            if let Some(terminator) = self.close_block(opener, terminators) {
+                break terminator;
+            }
+
+            if let Some(stmt) = self.declaration() {
+                statements.push(stmt);
+            }
+            self.skip_line_breaks();
+        };
+        self.open_blocks.pop();
+
+        (statements, terminator)
+    }
+
+    // Counts the terminators that would not match, and the blocks left open,
+    // if the tokens after the current one were parsed inside `open_blocks`
+    fn unbalanced_after(&self, mut open_blocks: Vec<Vec<TokenType>>) -> usize {
+        let mut unbalanced = 0;
+        let mut previous = None;
+        for token in &self.tokens[self.index + 1..] {
+            let token_type = &token.token_type;
+            match token_type {
+                // ELSE IF continues the IF block that ELSE was part of
+                TokenType::If if previous == Some(&TokenType::Else) => {
+                    open_blocks.pop();
+                    open_blocks.push(vec![TokenType::Else, TokenType::EndIf]);
+                }
+                TokenType::If => open_blocks.push(vec![TokenType::Else, TokenType::EndIf]),
+                TokenType::While => open_blocks.push(vec![TokenType::EndWhile]),
+                TokenType::For => open_blocks.push(vec![TokenType::EndFor]),
+                TokenType::Repeat => open_blocks.push(vec![TokenType::Until]),
+                TokenType::Subroutine => open_blocks.push(vec![TokenType::EndSubroutine]),
+                TokenType::Record => open_blocks.push(vec![TokenType::EndRecord]),
+                _ if Self::is_block_terminator(token_type) => {
+                    let closes = open_blocks
+                        .iter()
+                        .rposition(|terminators| terminators.contains(token_type));
+                    match closes {
+                        Some(closes) if closes == open_blocks.len() - 1 => {}
+                        Some(closes) => {
+                            unbalanced += 1;
+                            open_blocks.truncate(closes + 1);
+                        }
+                        None => unbalanced += 1,
+                    }
+                    if closes.is_some() {
+                        open_blocks.pop();
+                        if *token_type == TokenType::Else {
+                            open_blocks.push(vec![TokenType::EndIf]);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            previous = Some(token_type);
+        }
+
+        unbalanced + open_blocks.len()
+    }
+
+    fn is_block_terminator(token_type: &TokenType) -> bool {
+        matches!(
+            token_type,
+            TokenType::Else
+                | TokenType::EndIf
+                | TokenType::EndFor
+                | TokenType::EndWhile
+                | TokenType::Until
+                | TokenType::EndSubroutine
+                | TokenType::EndRecord
+        )
+    }
+
+    fn declaration(&mut self) -> Option<Stmt> {
+        let result = self.statement().and_then(|stmt| {
+            self.end_of_statement()?;
+            Ok(stmt)
+        });
+
+        match result {
+            Ok(stmt) => stmt,
+            Err(error) => {
+                self.errors.push(error);
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    // Statements whose header failed to parse are dropped, their errors having already been recorded
+    fn statement(&mut self) -> Result<Option<Stmt>> {
+        let start = self.peek().span;
+        let stmt_type = self.statement_type()?;
+        Ok(stmt_type.map(|stmt_type| Stmt {
+            stmt_type,
+            span: start.to(self.previous().span),
+        }))
+    }
+
+    fn statement_type(&mut self) -> Result<Option<StmtType>> {
+        if self.match_token(TokenType::Constant) {
+            self.constant_declaration().map(Some)
+        } else if self.match_token(TokenType::Output) {
+            self.output_statement().map(Some)
+        } else if self.match_token(TokenType::If) {
+            let opener = self.previous();
+            Ok(self.if_statement(&opener))
+        } else if self.match_token(TokenType::For) {
+            Ok(self.for_statement())
+        } else if self.match_token(TokenType::While) {
+            Ok(self.while_statement())
+        } else if self.match_token(TokenType::Repeat) {
+            self.repeat_statement()
+        } else if self.match_token(TokenType::Subroutine) {
+            Ok(self.subroutine_declaration())
+        } else if self.match_token(TokenType::Record) {
+            Ok(self.record_declaration())
+        } else if self.match_token(TokenType::Return) {
+            self.return_statement().map(Some)
+        } else {
+            self.expression_statement().map(Some)
+        }
+    }
+
+    fn constant_declaration(&mut self) -> Result<StmtType> {
+        let name = self.identifier()?;
+        let annotation = if self.match_token(TokenType::Colon) {
+            Some(self.annotation()?)
+        } else {
+            None
+        };
+        self.consume(TokenType::Assign)?;
+        let value = self.expression()?;
+        Ok(StmtType::Constant(name, annotation, value))
+    }
+
+    // Any name other than the built-in types refers to a record
+    fn annotation(&mut self) -> Result<Annotation> {
+        let name = self.identifier()?;
+        let mut annotation = match name.name.as_str() {
+            "Integer" => Annotation::Integer,
+            "Real" => Annotation::Real,
+            "Boolean" => Annotation::Boolean,
+            "String" => Annotation::String,
+            "Char" => Annotation::Char,
+            _ => Annotation::Record(name.name),
+        };
+
+        while self.match_token(TokenType::LeftBracket) {
+            self.consume(TokenType::RightBracket)?;
+            annotation = Annotation::Array(Box::new(annotation));
+        }
+
+        Ok(annotation)
+    }
+
+    fn parameter(&mut self) -> Result<Parameter> {
+        let name = self.identifier()?;
+        let annotation = if self.match_token(TokenType::Colon) {
+            Some(self.annotation()?)
+        } else {
+            None
+        };
+        Ok(Parameter { name, annotation })
+    }
+
+    fn if_statement(&mut self, opener: &Token) -> Option<StmtType> {
+        let condition = self.header(|parser| {
+            let condition = parser.expression()?;
+            parser.consume(TokenType::Then)?;
+            Ok(condition)
+        });
+
+        let (then_branch, terminator) = self.block(opener, &[TokenType::Else, TokenType::EndIf]);
+
+        let else_branch = if terminator.token_type == TokenType::Else {
+            if self.match_token(TokenType::If) {
+                let start = self.previous().span;
+                match self.if_statement(opener) {
+                    Some(stmt_type) => vec![Stmt {
+                        stmt_type,
+                        span: start.to(self.previous().span),
+                    }],
+                    None => Vec::new(),
+                }
+            } else {
+                self.block(opener, &[TokenType::EndIf]).0
+            }
+        } else {
+            Vec::new()
+        };
+
+        Some(StmtType::If(condition?, then_branch, else_branch))
+    }
+
+    fn for_statement(&mut self) -> Option<StmtType> {
+        let opener = self.previous();
+        let header = self.header(|parser| {
+            let name = parser.identifier()?;
+
+            if parser.match_token(TokenType::In) {
+                return Ok(ForHeader::In(name, parser.expression()?));
+            }
+
+            parser.consume(TokenType::Assign)?;
+            let start = parser.expression()?;
+            parser.consume(TokenType::To)?;
+            let end = parser.expression()?;
+            let step = if parser.match_token(TokenType::Step) {
+                Some(parser.expression()?)
+            } else {
+                None
+            };
+
+            Ok(ForHeader::Range(name, start, end, step))
+        });
+
+        let (body, _) = self.block(&opener, &[TokenType::EndFor]);
+
+        match header? {
+            ForHeader::Range(name, start, end, step) => {
+                Some(StmtType::For(name, start, end, step, body))
+            }
+            ForHeader::In(name, iterable) => Some(StmtType::ForIn(name, iterable, body)),
+        }
+    }
+
+    fn while_statement(&mut self) -> Option<StmtType> {
+        let opener = self.previous();
+        let condition = self.header(|parser| parser.expression());
+        let (body, _) = self.block(&opener, &[TokenType::EndWhile]);
+        Some(StmtType::While(condition?, body))
+    }
+
+    fn repeat_statement(&mut self) -> Result<Option<StmtType>> {
+        let opener = self.previous();
+        let (body, terminator) = self.block(&opener, &[TokenType::Until]);
+        if terminator.token_type != TokenType::Until {
+            return Ok(None);
+        }
+
+        let condition = self.expression()?;
+        Ok(Some(StmtType::Repeat(body, condition)))
+    }
+
+    fn subroutine_declaration(&mut self) -> Option<StmtType> {
+        let opener = self.previous();
+        if self.in_subroutine {
+            self.errors
+                .push(Error::new(opener.span, ErrorType::NestedSubroutine));
+        }
+
+        let header = self.header(|parser| {
+            let name = parser.identifier()?;
+            parser.consume(TokenType::LeftParen)?;
+
+            let mut parameters = Vec::new();
+            if !parser.check(TokenType::RightParen) {
+                parameters.push(parser.parameter()?);
+                while parser.match_token(TokenType::Comma) {
+                    parameters.push(parser.parameter()?);
+                }
+            }
+            parser.consume(TokenType::RightParen)?;
+
+            let returns = if parser.match_token(TokenType::Colon) {
+                Some(parser.annotation()?)
+            } else {
+                None
+            };
+
+            Ok((name, parameters, returns))
+        });
+
+        let in_subroutine = mem::replace(&mut self.in_subroutine, true);
+        let (body, _) = self.block(&opener, &[TokenType::EndSubroutine]);
+        self.in_subroutine = in_subroutine;
+
+        let (name, parameters, returns) = header?;
+        Some(StmtType::Subroutine(Rc::new(Subroutine {
+            name,
+            parameters,
+            returns,
+            body,
+        })))
+    }
+
+    fn record_declaration(&mut self) -> Option<StmtType> {
+        let opener = self.previous();
+        let name = self.header(|parser| parser.identifier());
+
+        let mut fields = Vec::new();
+        self.open_block(&[TokenType::EndRecord]);
+        while self.close_block(&opener, &[TokenType::EndRecord]).is_none() {
+            let result = self.record_field().and_then(|field| {
+                self.end_of_statement()?;
+                Ok(field)
+            });
+
+            match result {
+                Ok(field) => fields.push(field),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.synchronize();
+                }
+            }
+            self.skip_line_breaks();
+        }
+        self.open_blocks.pop();
+
+        Some(StmtType::Record(Rc::new(RecordDefinition {
+            name: name?,
+            fields,
+        })))
+    }
+
+    fn record_field(&mut self) -> Result<RecordField> {
+        let name = self.identifier()?;
+        self.consume(TokenType::Colon)?;
+        let annotation = self.annotation()?;
+        Ok(RecordField { name, annotation })
+    }
+
+    fn return_statement(&mut self) -> Result<StmtType> {
+        let keyword = self.previous();
+        if !self.in_subroutine {
+            self.errors
+                .push(Error::new(keyword.span, ErrorType::ReturnOutsideSubroutine));
+        }
+
+        let value = if self.check(TokenType::LineBreak) || self.check(TokenType::Eof) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+
+        Ok(StmtType::Return(value))
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Expr>> {
+        let mut arguments = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            arguments.push(self.expression()?);
+            while self.match_token(TokenType::Comma) {
+                arguments.push(self.expression()?);
+            }
+        }
+        self.consume(TokenType::RightParen)?;
+
+        Ok(arguments)
+    }
+
+    fn output_statement(&mut self) -> Result<StmtType> {
+        let mut values = vec![self.expression()?];
+        while self.match_token(TokenType::Comma) {
+            values.push(self.expression()?);
+        }
+        Ok(StmtType::Output(values))
+    }
+
+    fn expression_statement(&mut self) -> Result<StmtType> {
+        let expr = self.expression()?;
+
+        if let ExprType::Variable(name) = &expr.expr_type {
+            if self.match_token(TokenType::Colon) {
+                let annotation = self.annotation()?;
+                let value = if self.match_token(TokenType::Assign) {
+                    Some(self.expression()?)
+                } else {
+                    None
+                };
+                return Ok(StmtType::Declare(name.clone(), annotation, value));
+            }
+        }
+
+        if self.check(TokenType::Assign) {
+            let operator = self.advance();
+            let value = self.expression()?;
+
+            match expr.expr_type {
+                ExprType::Variable(name) => Ok(StmtType::Assign(name, value)),
+                ExprType::Index(array, index) => Ok(StmtType::AssignIndex(*array, *index, value)),
+                ExprType::Field(record, field) => Ok(StmtType::AssignField(*record, field, value)),
+                _ => Err(Error::new(
+                    expr.span.to(operator.span),
+                    ErrorType::InvalidAssignmentTarget,
+                )),
+            }
+        } else {
+            Ok(StmtType::Expression(expr))
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr> {
+        self.logical_or()
+    }
+
+    fn logical_or(&mut self) -> Result<Expr> {
+        let mut expr = self.logical_and()?;
+
+        while self.match_token(TokenType::LogicalOr) {
+            let operator = self.previous();
+            let right = self.logical_and()?;
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprType::Logical(Box::new(expr), operator, Box::new(right)),
+                span,
+            );
+        }
+
+        Ok(expr)
+    }
+
+    fn logical_and(&mut self) -> Result<Expr> {
+        let mut expr = self.equality()?;
+
+        while self.match_token(TokenType::LogicalAnd) {
+            let operator = self.previous();
+            let right = self.equality()?;
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprType::Logical(Box::new(expr), operator, Box::new(right)),
+                span,
+            );
+        }
+
+        Ok(expr)
     }
 
-    fn equality(&mut self) -> Result<ExprType> {
+    fn equality(&mut self) -> Result<Expr> {
         let mut expr = self.comparison()?;
 
         while self.match_token(TokenType::EqualTo) || self.match_token(TokenType::NotEqualTo) {
             let operator = self.previous();
             let right = self.comparison()?;
-            expr = ExprType::Binary(Box::new(expr), operator, Box::new(right));
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprType::Binary(Box::new(expr), operator, Box::new(right)),
+                span,
+            );
         }
 
         Ok(expr)
     }
 
-    fn comparison(&mut self) -> Result<ExprType> {
+    fn comparison(&mut self) -> Result<Expr> {
         let mut expr = self.term()?;
 
         while self.match_token(TokenType::LessThan)
@@ -88,75 +887,143 @@ impl Parser {
         {
             let operator = self.previous();
             let right = self.term()?;
-            expr = ExprType::Binary(Box::new(expr), operator, Box::new(right));
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprType::Binary(Box::new(expr), operator, Box::new(right)),
+                span,
+            );
         }
 
         Ok(expr)
     }
 
-    fn term(&mut self) -> Result<ExprType> {
+    fn term(&mut self) -> Result<Expr> {
         let mut expr = self.factor()?;
 
         while self.match_token(TokenType::Subtract) || self.match_token(TokenType::Add) {
             let operator = self.previous();
             let right = self.factor()?;
-            expr = ExprType::Binary(Box::new(expr), operator, Box::new(right));
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprType::Binary(Box::new(expr), operator, Box::new(right)),
+                span,
+            );
         }
 
         Ok(expr)
     }
 
-    fn factor(&mut self) -> Result<ExprType> {
+    fn factor(&mut self) -> Result<Expr> {
         let mut expr = self.unary()?;
 
-        while self.match_token(TokenType::Multiply) || self.match_token(TokenType::Divide) {
+        while self.match_token(TokenType::Multiply)
+            || self.match_token(TokenType::Divide)
+            || self.match_token(TokenType::IntDivide)
+            || self.match_token(TokenType::Modulus)
+        {
             let operator = self.previous();
             let right = self.unary()?;
-            expr = ExprType::Binary(Box::new(expr), operator, Box::new(right));
+            let span = expr.span.to(right.span);
+            expr = Expr::new(
+                ExprType::Binary(Box::new(expr), operator, Box::new(right)),
+                span,
+            );
         }
 
         Ok(expr)
     }
 
-    fn unary(&mut self) -> Result<ExprType> {
-        if self.match_token(TokenType::LogicalNot) || self.match_token(TokenType::LogicalAnd) {
+    fn unary(&mut self) -> Result<Expr> {
+        if self.match_token(TokenType::LogicalNot) || self.match_token(TokenType::Subtract) {
             let operator = self.previous();
             let right = self.unary()?;
-            Ok(ExprType::Unary(operator, Box::new(right)))
+            let span = operator.span.to(right.span);
+            Ok(Expr::new(ExprType::Unary(operator, Box::new(right)), span))
         } else {
-            self.primary()
+            self.postfix()
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Expr> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_token(TokenType::LeftBracket) {
+                let index = self.expression()?;
+                let end = self.consume(TokenType::RightBracket)?;
+                let span = expr.span.to(end.span);
+                expr = Expr::new(ExprType::Index(Box::new(expr), Box::new(index)), span);
+            } else if self.match_token(TokenType::Dot) {
+                let field = self.identifier()?;
+                let span = expr.span.to(field.span);
+                expr = Expr::new(ExprType::Field(Box::new(expr), field), span);
+            } else {
+                break;
+            }
         }
+
+        Ok(expr)
     }
 
-    fn primary(&mut self) -> Result<ExprType> {
-        let expr = match self.peek().token_type {
+    fn primary(&mut self) -> Result<Expr> {
+        let start = self.peek().span;
+        let expr_type = match self.peek().token_type {
             TokenType::Literal(value) => {
                 self.advance();
                 ExprType::Literal(value)
             }
 
+            TokenType::Identifier(name) => {
+                let token = self.advance();
+                let name = Identifier {
+                    name,
+                    span: token.span,
+                };
+
+                if self.match_token(TokenType::LeftParen) {
+                    ExprType::Call(name, self.arguments()?)
+                } else {
+                    ExprType::Variable(name)
+                }
+            }
+
+            TokenType::UserInput => {
+                self.advance();
+                ExprType::UserInput
+            }
+
+            TokenType::LeftBracket => {
+                self.advance();
+                let mut elements = Vec::new();
+                if !self.check(TokenType::RightBracket) {
+                    elements.push(self.expression()?);
+                    while self.match_token(TokenType::Comma) {
+                        elements.push(self.expression()?);
+                    }
+                }
+                self.consume(TokenType::RightBracket)?;
+                ExprType::Array(elements)
+            }
+
             TokenType::LeftParen => {
                 self.advance();
                 let expr = self.expression()?;
                 if !self.match_token(TokenType::RightParen) {
-                    return Err(Error::new(
-                        self.peek().position,
-                        ErrorType::ExpectedRightParen,
-                    ));
+                    return Err(Error::new(self.peek().span, ErrorType::ExpectedRightParen));
                 } else {
-                    expr
+                    expr.expr_type
                 }
             }
 
             _ => {
                 let token = self.peek();
                 return Err(Error::new(
-                    token.position,
-                    ErrorType::UnexpectedToken(token.clone()),
+                    token.span,
+                    ErrorType::UnexpectedToken(token.token_type.clone()),
                 ));
             }
         };
 
-        Ok(expr)
+        Ok(Expr::new(expr_type, start.to(self.previous().span)))
     }
 }
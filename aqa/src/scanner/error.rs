@@ -1,33 +1,49 @@
 use std::fmt;
 
+use crate::Span;
+
 #[derive(Debug)]
 pub enum ErrorType {
     UnexpectedEOF,
     UnexpectedChar(char),
     FailedToParseFloat,
     FailedToParseInt,
+    InvalidCharLiteral(String),
 }
 
 #[derive(Debug)]
 pub struct Error {
-    pub line: usize,
-    pub column: usize,
+    pub span: Span,
     pub error_type: ErrorType,
 }
 
-impl fmt::Display for Error {
+impl fmt::Display for ErrorType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let error_message = match &self.error_type {
+        let message = match self {
             ErrorType::UnexpectedEOF => "Unexpected EOF".to_string(),
             ErrorType::UnexpectedChar(char) => format!("Unexpected character: '{}'", char),
             ErrorType::FailedToParseFloat => "failed to parse float".to_string(),
             ErrorType::FailedToParseInt => "failed to parse int".to_string(),
+            ErrorType::InvalidCharLiteral(contents) => {
+                format!(
+                    "A character literal must hold one character, found \"{}\"",
+                    contents
+                )
+            }
         };
 
+        write!(f, "{}", message)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "at line {}, column {}: {}",
-            self.line, self.column, error_message
+            self.span.start().line(),
+            self.span.start().column(),
+            self.error_type
         )
     }
 }
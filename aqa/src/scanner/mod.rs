@@ -1,12 +1,12 @@
 mod error;
 pub use error::*;
 
-use crate::{Position, Value};
+use crate::{Position, Span, Value};
 
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
-    pub position: Position,
+    pub span: Span,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -43,6 +43,7 @@ pub enum TokenType {
 
     For,
     To,
+    Step,
     In,
     EndFor,
 
@@ -52,19 +53,101 @@ pub enum TokenType {
     EndIf,
 
     Output,
+    UserInput,
+
+    Subroutine,
+    EndSubroutine,
+    Return,
+
+    Record,
+    EndRecord,
 
     LineBreak,
     Eof,
 
     LeftParen,
     RightParen,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    Colon,
+    Dot,
+}
+
+impl TokenType {
+    pub fn keyword(&self) -> Option<&'static str> {
+        let keyword = match self {
+            TokenType::Constant => "CONSTANT",
+            TokenType::IntDivide => "DIV",
+            TokenType::Modulus => "MOD",
+            TokenType::LogicalAnd => "AND",
+            TokenType::LogicalOr => "OR",
+            TokenType::LogicalNot => "NOT",
+            TokenType::Repeat => "REPEAT",
+            TokenType::Until => "UNTIL",
+            TokenType::While => "WHILE",
+            TokenType::EndWhile => "ENDWHILE",
+            TokenType::For => "FOR",
+            TokenType::To => "TO",
+            TokenType::Step => "STEP",
+            TokenType::In => "IN",
+            TokenType::EndFor => "ENDFOR",
+            TokenType::If => "IF",
+            TokenType::Then => "THEN",
+            TokenType::Else => "ELSE",
+            TokenType::EndIf => "ENDIF",
+            TokenType::Output => "OUTPUT",
+            TokenType::UserInput => "USERINPUT",
+            TokenType::Subroutine => "SUBROUTINE",
+            TokenType::EndSubroutine => "ENDSUBROUTINE",
+            TokenType::Return => "RETURN",
+            TokenType::Record => "RECORD",
+            TokenType::EndRecord => "ENDRECORD",
+            _ => return None,
+        };
+
+        Some(keyword)
+    }
+
+    /// How the token is written in the source, quoted, for error messages.
+    pub fn describe(&self) -> String {
+        let symbol = match self {
+            TokenType::Literal(Value::String(string)) => return format!("'{}'", string),
+            TokenType::Literal(Value::Char(char)) => return format!("\"{}\"", char),
+            TokenType::Literal(value) => return format!("'{}'", value),
+            TokenType::Identifier(name) => return format!("'{}'", name),
+            TokenType::LineBreak => return String::from("end of line"),
+            TokenType::Eof => return String::from("end of file"),
+            TokenType::Assign => "<-",
+            TokenType::Add => "+",
+            TokenType::Subtract => "-",
+            TokenType::Multiply => "*",
+            TokenType::Divide => "/",
+            TokenType::LessThan => "<",
+            TokenType::GreaterThan => ">",
+            TokenType::EqualTo => "=",
+            TokenType::NotEqualTo => "!=",
+            TokenType::LessThanOrEqualTo => "<=",
+            TokenType::GreaterThanOrEqualTo => ">=",
+            TokenType::LeftParen => "(",
+            TokenType::RightParen => ")",
+            TokenType::LeftBracket => "[",
+            TokenType::RightBracket => "]",
+            TokenType::Comma => ",",
+            TokenType::Colon => ":",
+            TokenType::Dot => ".",
+            keyword => keyword.keyword().unwrap_or_default(),
+        };
+
+        format!("'{}'", symbol)
+    }
 }
 
 pub struct Scanner {
     contents: String,
     index: usize,
-    line: usize,
-    column: usize,
+    line: u32,
+    column: u32,
 }
 
 impl Scanner {
@@ -77,6 +160,14 @@ impl Scanner {
         }
     }
 
+    // Offsets stay relative to the whole of `contents`, lines and columns to `offset`
+    pub fn starting_at(contents: String, offset: usize) -> Self {
+        Self {
+            index: offset,
+            ..Self::new(contents)
+        }
+    }
+
     fn resolve_word(word: String) -> TokenType {
         match word.as_str() {
             "CONSTANT" => TokenType::Constant,
@@ -95,6 +186,7 @@ impl Scanner {
 
             "FOR" => TokenType::For,
             "TO" => TokenType::To,
+            "STEP" => TokenType::Step,
             "IN" => TokenType::In,
             "ENDFOR" => TokenType::EndFor,
 
@@ -104,6 +196,14 @@ impl Scanner {
             "ENDIF" => TokenType::EndIf,
 
             "OUTPUT" => TokenType::Output,
+            "USERINPUT" => TokenType::UserInput,
+
+            "SUBROUTINE" => TokenType::Subroutine,
+            "ENDSUBROUTINE" => TokenType::EndSubroutine,
+            "RETURN" => TokenType::Return,
+
+            "RECORD" => TokenType::Record,
+            "ENDRECORD" => TokenType::EndRecord,
 
             "True" => TokenType::Literal(Value::Bool(true)),
             "False" => TokenType::Literal(Value::Bool(false)),
@@ -113,9 +213,9 @@ impl Scanner {
     }
 
     fn next(&mut self) -> Option<char> {
-        let result = self.contents.chars().nth(self.index);
-        self.index += 1;
+        let result = self.peek();
         if let Some(char) = result {
+            self.index += char.len_utf8();
             if char == '\n' {
                 self.line += 1;
                 self.column = 1;
@@ -127,16 +227,56 @@ impl Scanner {
         result
     }
 
+    // Reads the rest of a literal opened by `quote`, or `None` if the file
+    // ends first. A backslash makes the next character literal, except that
+    // '\n' is a line break
+    fn quoted(&mut self, quote: char) -> Option<String> {
+        let mut contents = String::new();
+
+        let mut escape = false;
+        while let Some(char) = self.next() {
+            match char {
+                '\\' if !escape => {
+                    escape = true;
+                    continue;
+                }
+                _ if char == quote && !escape => return Some(contents),
+                'n' if escape => contents.push('\n'),
+                _ => contents.push(char),
+            }
+            escape = false;
+        }
+
+        None
+    }
+
     fn peek(&mut self) -> Option<char> {
-        self.contents.chars().nth(self.index)
+        self.contents[self.index..].chars().next()
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+            offset: self.index as u32,
+        }
     }
 
     pub fn scan_token(&mut self) -> Result<Token> {
+        while let Some(char) = self.peek() {
+            if char.is_ascii_whitespace() && char != '\n' {
+                self.next();
+            } else {
+                break;
+            }
+        }
+
+        let start = self.position();
+
         macro_rules! error_value {
             ($error_type:expr) => {
                 Err(Error {
-                    line: self.line,
-                    column: self.column,
+                    span: Span::new(start, self.position()),
                     error_type: $error_type,
                 })
             };
@@ -151,16 +291,11 @@ impl Scanner {
             };
         }
 
-        let mut starting_column = self.column;
-
         macro_rules! token_value {
             ($token_type:expr) => {
                 Ok(Token {
                     token_type: $token_type,
-                    position: Position {
-                        line: self.line,
-                        column: starting_column,
-                    },
+                    span: Span::new(start, self.position()),
                 })
             };
         }
@@ -174,165 +309,140 @@ impl Scanner {
         }
 
         if let Some(char) = self.next() {
-            let mut char = char;
-
-            let mut is_end = false;
-            while char.is_ascii_whitespace() && !is_end {
-                if let Some(next_char) = self.next() {
-                    char = next_char;
-                } else {
-                    is_end = true;
-                }
-            }
-
-            starting_column = self.column - 1;
-
-            if is_end {
-                token!(Eof)
-            } else {
-                match char {
-                    '<' => {
-                        if let Some(next) = self.peek() {
-                            match next {
-                                '-' => {
-                                    self.index += 1;
-                                    token!(Assign)
-                                }
-                                '=' => {
-                                    self.index += 1;
-                                    token!(LessThanOrEqualTo)
-                                }
-                                _ => token!(LessThan),
+            match char {
+                '<' => {
+                    if let Some(next) = self.peek() {
+                        match next {
+                            '-' => {
+                                self.next();
+                                token!(Assign)
                             }
-                        } else {
-                            token!(LessThan)
+                            '=' => {
+                                self.next();
+                                token!(LessThanOrEqualTo)
+                            }
+                            _ => token!(LessThan),
                         }
+                    } else {
+                        token!(LessThan)
                     }
+                }
 
-                    '>' => {
-                        if let Some(next) = self.peek() {
-                            match next {
-                                '=' => {
-                                    self.index += 1;
-                                    token!(GreaterThanOrEqualTo)
-                                }
-                                _ => token!(GreaterThan),
+                '>' => {
+                    if let Some(next) = self.peek() {
+                        match next {
+                            '=' => {
+                                self.next();
+                                token!(GreaterThanOrEqualTo)
                             }
-                        } else {
-                            token!(GreaterThan)
+                            _ => token!(GreaterThan),
                         }
+                    } else {
+                        token!(GreaterThan)
                     }
+                }
 
-                    '!' => {
-                        if let Some(next) = self.peek() {
-                            match next {
-                                '=' => {
-                                    self.index += 1;
-                                    token!(NotEqualTo)
-                                }
-                                _ => error!(UnexpectedChar(next)),
+                '!' => {
+                    if let Some(next) = self.peek() {
+                        match next {
+                            '=' => {
+                                self.next();
+                                token!(NotEqualTo)
                             }
-                        } else {
-                            error!(UnexpectedEOF)
+                            _ => error!(UnexpectedChar(next)),
                         }
+                    } else {
+                        error!(UnexpectedEOF)
                     }
+                }
 
-                    '+' => token!(Add),
-                    '-' => token!(Subtract),
-                    '*' => token!(Multiply),
-                    '/' => token!(Divide),
-
-                    '=' => token!(EqualTo),
-
-                    '\n' => token!(LineBreak),
-
-                    '(' => token!(LeftParen),
-                    ')' => token!(RightParen),
-
-                    '\'' => {
-                        let mut string = String::new();
-
-                        let mut is_end = false;
-                        let mut escape = false;
-                        while let Some(char) = self.next() {
-                            match char {
-                                '\\' if !escape => {
-                                    escape = true;
-                                }
-                                '\'' if !escape => {
-                                    is_end = true;
-                                    break;
-                                }
-                                'n' if escape => {
-                                    string.push('\n');
-                                }
-                                _ => {
-                                    string.push(char);
-                                }
-                            }
+                '+' => token!(Add),
+                '-' => token!(Subtract),
+                '*' => token!(Multiply),
+                '/' => token!(Divide),
+
+                '=' => token!(EqualTo),
+
+                '\n' => token!(LineBreak),
+
+                '(' => token!(LeftParen),
+                ')' => token!(RightParen),
+                '[' => token!(LeftBracket),
+                ']' => token!(RightBracket),
+                ',' => token!(Comma),
+                ':' => token!(Colon),
+                '.' => token!(Dot),
+
+                '\'' => match self.quoted('\'') {
+                    Some(string) => token!(Literal(Value::String(string))),
+                    None => error!(UnexpectedEOF),
+                },
+
+                '"' => match self.quoted('"') {
+                    Some(contents) => {
+                        let mut chars = contents.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(char), None) => token!(Literal(Value::Char(char))),
+                            _ => error!(InvalidCharLiteral(contents)),
                         }
-
-                        if is_end {
-                            token!(Literal(Value::String(string)))
+                    }
+                    None => error!(UnexpectedEOF),
+                },
+
+                _ if char.is_ascii_digit() => {
+                    let mut string = String::new();
+                    string.push(char);
+
+                    let mut is_float = false;
+                    while let Some(char) = self.peek() {
+                        if char.is_numeric() {
+                            self.next();
+                            string.push(char);
+                        } else if char == '.' {
+                            self.next();
+                            is_float = true;
+                            string.push(char);
+                        } else if !char.is_alphabetic() {
+                            break;
                         } else {
-                            error!(UnexpectedEOF)
+                            return error!(UnexpectedChar(char));
                         }
                     }
 
-                    _ if char.is_ascii_digit() => {
-                        let mut string = String::new();
-                        string.push(char);
-
-                        let mut is_float = false;
-                        while let Some(char) = self.peek() {
-                            if char.is_numeric() {
-                                self.next();
-                                string.push(char);
-                            } else if char == '.' {
-                                self.next();
-                                is_float = true;
-                                string.push(char);
-                            } else if !char.is_alphabetic() {
-                                break;
-                            } else {
-                                return error!(UnexpectedChar(char));
-                            }
+                    if is_float {
+                        let parse_result = string.parse::<f64>();
+                        if let Ok(result) = parse_result {
+                            token!(Literal(Value::Float(result)))
+                        } else {
+                            error!(FailedToParseFloat)
                         }
-
-                        if is_float {
-                            let parse_result = string.parse::<f64>();
-                            if let Ok(result) = parse_result {
-                                token!(Literal(Value::Float(result)))
-                            } else {
-                                error!(FailedToParseFloat)
-                            }
+                    } else {
+                        let parse_result = string.parse::<i64>();
+                        if let Ok(result) = parse_result {
+                            token!(Literal(Value::Int(result)))
                         } else {
-                            let parse_result = string.parse::<i64>();
-                            if let Ok(result) = parse_result {
-                                token!(Literal(Value::Int(result)))
-                            } else {
-                                error!(FailedToParseInt)
-                            }
+                            error!(FailedToParseInt)
                         }
                     }
+                }
 
-                    _ if char.is_alphabetic() => {
-                        let mut string = String::new();
-                        string.push(char);
+                _ if char.is_alphabetic() => {
+                    let mut string = String::new();
+                    string.push(char);
 
-                        while let Some(char) = self.next() {
-                            if char.is_alphanumeric() {
-                                string.push(char);
-                            } else {
-                                break;
-                            }
+                    while let Some(char) = self.peek() {
+                        if char.is_alphanumeric() || char == '_' {
+                            self.next();
+                            string.push(char);
+                        } else {
+                            break;
                         }
-
-                        token_value!(Self::resolve_word(string))
                     }
 
-                    _ => error!(UnexpectedChar(char)),
+                    token_value!(Self::resolve_word(string))
                 }
+
+                _ => error!(UnexpectedChar(char)),
             }
         } else {
             token!(Eof)
@@ -0,0 +1,181 @@
+fn errors(source: &str) -> Vec<String> {
+    aqa::check_types(source.to_string())
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect()
+}
+
+fn assert_accepts(source: &str) {
+    assert_eq!(errors(source), Vec::<String>::new(), "{}", source);
+}
+
+#[test]
+fn mismatched_operands() {
+    assert_eq!(
+        errors("OUTPUT 'a' + 1\n"),
+        ["Cannot apply '+' to String and Integer"]
+    );
+    assert_eq!(
+        errors("OUTPUT NOT 3\nOUTPUT 1 AND True\n"),
+        [
+            "Cannot apply 'NOT' to Integer",
+            "Expected a Boolean, found Integer"
+        ]
+    );
+}
+
+#[test]
+fn non_boolean_conditions() {
+    assert_eq!(
+        errors("IF 1 THEN\n\tOUTPUT 1\nENDIF\nWHILE 'x'\nENDWHILE\nREPEAT\nUNTIL 2.5\n"),
+        [
+            "Condition must be a Boolean, found Integer",
+            "Condition must be a Boolean, found String",
+            "Condition must be a Boolean, found Real"
+        ]
+    );
+}
+
+#[test]
+fn wrong_builtin_arguments() {
+    assert_eq!(
+        errors("OUTPUT LEN(1)\nOUTPUT LEN('a', 'b')\nOUTPUT CODE_TO_CHAR('a')\n"),
+        [
+            "'LEN' cannot accept Integer",
+            "'LEN' takes 1 argument(s) but 2 were given",
+            "'CODE_TO_CHAR' cannot accept String"
+        ]
+    );
+}
+
+#[test]
+fn errors_inside_loops_are_reported_once() {
+    assert_eq!(
+        errors("total <- 0\nFOR i <- 1 TO 3\n\ttotal <- total + i\n\tOUTPUT i + 'a'\nENDFOR\n"),
+        ["Cannot apply '+' to Integer and String"]
+    );
+}
+
+#[test]
+fn variable_reassigned_on_one_branch() {
+    assert_accepts(
+        "x <- 1\nIF USERINPUT = 'y' THEN\n\tx <- 'text'\nENDIF\nOUTPUT x + 1\nOUTPUT x + 'more'\n",
+    );
+}
+
+#[test]
+fn variable_widened_by_a_loop() {
+    assert_accepts("x <- 0\nWHILE x < 3\n\tx <- x + 0.5\nENDWHILE\nOUTPUT x - 1\n");
+}
+
+#[test]
+fn aliased_arrays() {
+    assert_accepts("a <- [1, 2]\nb <- a\nb[0] <- 'x'\nOUTPUT a[0] + 'y'\n");
+}
+
+#[test]
+fn subroutines_returning_different_types() {
+    assert_accepts(
+        "SUBROUTINE f(n)\n\tIF n > 0 THEN\n\t\tRETURN 'positive'\n\tENDIF\n\tRETURN n\nENDSUBROUTINE\nOUTPUT f(1) + 'x'\nOUTPUT f(-1) + 1\n",
+    );
+}
+
+#[test]
+fn arrays_widened_by_a_declaration() {
+    assert_accepts("a <- [1, 2]\nx : Real[] <- a\nOUTPUT REAL_TO_STRING(a[0])\n");
+    assert_eq!(
+        errors("x : Real[] <- [1, 2]\nOUTPUT x[0] DIV 1\n"),
+        ["Cannot apply 'DIV' to Real and Integer"]
+    );
+}
+
+#[test]
+fn values_that_cannot_have_the_declared_type() {
+    assert_eq!(
+        errors("x : Integer <- 'a'\nCONSTANT y : Boolean <- 1.5\nz : Real <- 1\nz <- [1]\n"),
+        [
+            "Expected Integer for 'x', found String",
+            "Expected Boolean for 'y', found Real",
+            "Expected Real for 'z', found Array of Integer"
+        ]
+    );
+    assert_eq!(
+        errors("SUBROUTINE f(n : Integer)\n\tOUTPUT n\nENDSUBROUTINE\nRECORD P\n\ta : Real\nENDRECORD\nf('s')\np <- P(True)\n"),
+        [
+            "Expected Integer for 'n', found String",
+            "Expected Real for 'a', found Boolean"
+        ]
+    );
+    assert_accepts(
+        "x : Real <- 1\nc : Char <- 'q'\nSUBROUTINE f(n : Real)\n\tOUTPUT n\nENDSUBROUTINE\nf(2)\n",
+    );
+}
+
+#[test]
+fn declarations_on_one_branch() {
+    assert_accepts("IF USERINPUT = 'y' THEN\n\tx : Integer <- 1\nENDIF\nx <- 'text'\n");
+    assert_eq!(
+        errors("IF USERINPUT = 'y' THEN\n\tx : Integer <- 1\nELSE\n\tx : Integer <- 2\nENDIF\nx <- 'text'\n"),
+        ["Expected Integer for 'x', found String"]
+    );
+}
+
+#[test]
+fn stores_into_declared_arrays_and_fields() {
+    assert_eq!(
+        errors("x : Integer[] <- [1, 2]\nx[0] <- 'a'\nRECORD P\n\tv : Integer[]\n\tn : Real\nENDRECORD\np <- P([1], 2)\np.v[0] <- 'x'\np.n <- True\n"),
+        [
+            "Expected Integer for 'x', found String",
+            "Expected Integer for 'v', found String",
+            "Expected Real for 'n', found Boolean"
+        ]
+    );
+    assert_accepts("x : Real[] <- [1, 2]\nx[0] <- 5\nOUTPUT REAL_TO_STRING(x[0])\n");
+}
+
+#[test]
+fn unknown_types() {
+    assert_eq!(
+        errors("x : integer <- 1\nRECORD P\n\ta : Pt\nENDRECORD\n"),
+        ["Unknown type 'integer'", "Unknown type 'Pt'"]
+    );
+}
+
+#[test]
+fn slash_gives_a_real() {
+    assert_eq!(
+        errors("x <- 7 / 2\nOUTPUT x DIV 2\n"),
+        ["Cannot apply 'DIV' to Real and Integer"]
+    );
+}
+
+#[test]
+fn subroutines_declared_inside_blocks() {
+    assert_eq!(
+        errors("IF True THEN\n\tSUBROUTINE f()\n\t\tRETURN 1 + 'a'\n\tENDSUBROUTINE\n\tOUTPUT f(1)\nENDIF\n"),
+        [
+            "Cannot apply '+' to Integer and String",
+            "'f' takes 0 argument(s) but 1 were given"
+        ]
+    );
+}
+
+#[test]
+fn builtin_signatures() {
+    assert_accepts("OUTPUT LEN('abc') + CHAR_TO_CODE('a')\nOUTPUT RANDOM_INT(1, 6)\n");
+    assert_eq!(
+        errors("OUTPUT LEN(3)\nOUTPUT STRING_TO_INT('1', 2)\n"),
+        [
+            "'LEN' cannot accept Integer",
+            "'STRING_TO_INT' takes 1 argument(s) but 2 were given"
+        ]
+    );
+}
+
+#[test]
+fn fields_a_record_does_not_declare() {
+    assert_eq!(
+        errors("RECORD P\n\ty: Integer\nENDRECORD\np <- P(1)\nOUTPUT p.x\np.x <- 2\n"),
+        ["Record 'P' has no field 'x'", "Record 'P' has no field 'x'"]
+    );
+}
@@ -0,0 +1,49 @@
+use aqa::ColorMode;
+
+fn diagnostic(source: &str) -> aqa::Diagnostic {
+    aqa::check(source.to_string()).remove(0)
+}
+
+#[test]
+fn underlines_the_span() {
+    let source = "x <- 1 2\n";
+    assert_eq!(
+        diagnostic(source).render("test.aqa", source, ColorMode::Plain),
+        "error: Expected end of line, found '2'\n --> test.aqa:1:8\n  |\n1 | x <- 1 2\n  |        ^\n  = help: each statement must start on a new line\n"
+    );
+}
+
+#[test]
+fn span_outside_the_source_has_no_snippet() {
+    let diagnostic = diagnostic("OUTPUT 1\nOUTPUT 2\nx <- 1 2\n");
+    assert_eq!(
+        diagnostic.render("test.aqa", "OUTPUT 1\n", ColorMode::Plain),
+        "error: Expected end of line, found '2'\n --> test.aqa:3:8\n  = help: each statement must start on a new line\n"
+    );
+}
+
+#[test]
+fn span_inside_a_character_is_clamped() {
+    // The error is at byte 7, which falls inside the fourth 'é'
+    let diagnostic = diagnostic("x <- 1 2\n");
+    let rendered = diagnostic.render("test.aqa", "éééé\n", ColorMode::Plain);
+    assert!(rendered.contains("1 | éééé\n  |    ^\n"), "{}", rendered);
+}
+
+#[test]
+fn error_at_the_end_of_a_crlf_line() {
+    let source = "x <- 1 +\r\nOUTPUT x\r\n";
+    assert_eq!(
+        diagnostic(source).render("test.aqa", source, ColorMode::Plain),
+        "error: Unexpected end of line\n --> test.aqa:1:10\n  |\n1 | x <- 1 +\n  |          ^\n"
+    );
+}
+
+#[test]
+fn joining_a_number_to_a_string_suggests_a_conversion() {
+    let diagnostic = aqa::check_types("OUTPUT 'a' + 1\n".to_string()).remove(0);
+    assert_eq!(
+        diagnostic.help.as_deref(),
+        Some("convert the Integer with INT_TO_STRING before joining it to a String")
+    );
+}
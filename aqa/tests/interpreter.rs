@@ -0,0 +1,505 @@
+use std::collections::VecDeque;
+
+use aqa::{Backend, RunOptions, TokenType, Value};
+
+const BACKENDS: [Backend; 2] = [Backend::TreeWalker, Backend::Bytecode];
+
+fn run(source: &str, backend: Backend) -> aqa::Result<Vec<String>> {
+    run_with(
+        source,
+        &RunOptions {
+            backend,
+            ..RunOptions::default()
+        },
+    )
+}
+
+fn run_with(source: &str, options: &RunOptions) -> aqa::Result<Vec<String>> {
+    let mut output = Vec::new();
+    let mut input = VecDeque::new();
+
+    aqa::run(source.to_string(), &mut output, &mut input, options).map(|run| run.output)
+}
+
+fn expect_error(source: &str, message: &str) {
+    for backend in BACKENDS {
+        match run(source, backend) {
+            Ok(output) => panic!("{:?} ran without an error: {:?}", backend, output),
+            Err(error) => assert!(
+                error.to_string().ends_with(message),
+                "{:?} reported '{}', expected '{}'",
+                backend,
+                error,
+                message
+            ),
+        }
+    }
+}
+
+// Runs on the test thread's default stack, like an embedding host would
+#[test]
+fn recursion_up_to_the_call_limit() {
+    let source = format!(
+        "SUBROUTINE f(n)\n\tIF n = 0 THEN\n\t\tRETURN 0\n\tENDIF\n\tRETURN f(n - 1) + 1\nENDSUBROUTINE\nOUTPUT f({})\n",
+        aqa::DEFAULT_MAX_CALL_DEPTH - 1
+    );
+
+    for backend in BACKENDS {
+        assert_eq!(
+            run(&source, backend).unwrap(),
+            [(aqa::DEFAULT_MAX_CALL_DEPTH - 1).to_string()]
+        );
+    }
+}
+
+#[test]
+fn configured_call_depth() {
+    let subroutine = "SUBROUTINE f(n)\n\tIF n > 0 THEN\n\t\tf(n - 1)\n\tENDIF\nENDSUBROUTINE\n";
+
+    for backend in BACKENDS {
+        let options = RunOptions {
+            backend,
+            max_call_depth: 5,
+            ..RunOptions::default()
+        };
+
+        let within = format!("{}f(4)\nOUTPUT 'done'\n", subroutine);
+        assert_eq!(run_with(&within, &options).unwrap(), ["done"]);
+
+        let error = run_with(&format!("{}f(5)\n", subroutine), &options).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .ends_with("at line 3, column 3: Stack overflow when calling 'f'"),
+            "{:?} reported '{}'",
+            backend,
+            error
+        );
+    }
+}
+
+#[test]
+fn unbounded_recursion() {
+    expect_error(
+        "SUBROUTINE f(n)\n\tRETURN f(n + 1)\nENDSUBROUTINE\nf(0)\n",
+        "Stack overflow when calling 'f'",
+    );
+}
+
+#[test]
+fn for_loop_stops_at_the_end_of_the_integer_range() {
+    let source = "FOR i <- 9223372036854775806 TO 9223372036854775807\n\tOUTPUT i\nENDFOR\nFOR i <- -9223372036854775807 TO -9223372036854775807 - 1 STEP -1\n\tOUTPUT i\nENDFOR\n";
+
+    for backend in BACKENDS {
+        assert_eq!(
+            run(source, backend).unwrap(),
+            [
+                "9223372036854775806",
+                "9223372036854775807",
+                "-9223372036854775807",
+                "-9223372036854775808"
+            ]
+        );
+    }
+}
+
+#[test]
+fn arrays_that_contain_themselves() {
+    let source = "p <- [1, 2]\np[0] <- p\nOUTPUT p\nOUTPUT [p, p]\n";
+
+    for backend in BACKENDS {
+        assert_eq!(
+            run(source, backend).unwrap(),
+            ["[[...], 2]", "[[[...], 2], [[...], 2]]"]
+        );
+    }
+}
+
+#[test]
+fn record_field_holding_its_own_record() {
+    let source =
+        "RECORD Node\n\tnext : Node[]\nENDRECORD\nn <- Node([])\nn.next <- [n]\nOUTPUT n\n";
+
+    for backend in BACKENDS {
+        assert_eq!(run(source, backend).unwrap(), ["Node(next: [Node(...)])"]);
+    }
+}
+
+#[test]
+fn integer_overflow() {
+    expect_error("OUTPUT 9223372036854775807 + 1\n", "Integer overflow");
+    expect_error("OUTPUT -9223372036854775807 - 2\n", "Integer overflow");
+    expect_error("OUTPUT 4611686018427387904 * 2\n", "Integer overflow");
+    expect_error(
+        "x <- -9223372036854775807 - 1\nOUTPUT x DIV -1\n",
+        "Integer overflow",
+    );
+    expect_error(
+        "x <- -9223372036854775807 - 1\nOUTPUT x MOD -1\n",
+        "Integer overflow",
+    );
+    expect_error(
+        "x <- -9223372036854775807 - 1\nOUTPUT -x\n",
+        "Integer overflow",
+    );
+}
+
+#[test]
+fn declared_types_convert_values() {
+    let source = "x : Real <- 3\nOUTPUT x, ' ', x / 2\nSUBROUTINE mean(values : Integer[]) : Real\n\tRETURN 150 DIV LEN(values)\nENDSUBROUTINE\nOUTPUT mean([70, 80])\n";
+
+    for backend in BACKENDS {
+        assert_eq!(run(source, backend).unwrap(), ["3.0 1.5", "75.0"]);
+    }
+}
+
+#[test]
+fn declared_type_errors() {
+    expect_error(
+        "x : Integer <- 1\nx <- 'a'\n",
+        "at line 2, column 1: Expected Integer for 'x', found String",
+    );
+    expect_error(
+        "x : Integer[] <- [1, 'a']\n",
+        "Expected Integer[] for 'x', found Array",
+    );
+    expect_error(
+        "SUBROUTINE f(a : Integer)\nENDSUBROUTINE\nf('s')\n",
+        "at line 3, column 3: Expected Integer for 'a', found String",
+    );
+    expect_error(
+        "SUBROUTINE f() : Boolean\n\tRETURN 1\nENDSUBROUTINE\nOUTPUT f()\n",
+        "at line 4, column 8: Expected Boolean for 'f', found Integer",
+    );
+    expect_error(
+        "RECORD P\n\ta : Real\nENDRECORD\np <- P('x')\n",
+        "Expected Real for 'a', found String",
+    );
+    expect_error("x : Integer\nOUTPUT x\n", "Undefined variable 'x'");
+    expect_error(
+        "CONSTANT n : Integer <- 1\nn : Integer <- 2\n",
+        "Cannot reassign constant 'n'",
+    );
+}
+
+#[test]
+fn char_literals() {
+    let source = "c : Char <- 'q'\nOUTPUT c + c, ' ', c < \"r\", ' ', c = 'q'\n";
+
+    for backend in BACKENDS {
+        assert_eq!(run(source, backend).unwrap(), ["qq True True"]);
+    }
+
+    expect_error(
+        "OUTPUT \"ab\"\n",
+        "A character literal must hold one character, found \"ab\"",
+    );
+    expect_error(
+        "OUTPUT \"\"\n",
+        "A character literal must hold one character, found \"\"",
+    );
+    expect_error("c : Char <- 'qq'\n", "Expected Char for 'c', found String");
+    expect_error("OUTPUT \"a\" + 1\n", "Mismatched Type");
+}
+
+#[test]
+fn declared_arrays_convert_their_elements() {
+    let source = "a <- [1, 2]\nx : Real[] <- a\nOUTPUT x, ' ', a\nm : Real[][] <- [[1], [2, 3.5]]\nOUTPUT m\nc : Char[] <- ['a', 'b']\nOUTPUT c[0] + c[1]\n";
+
+    for backend in BACKENDS {
+        assert_eq!(
+            run(source, backend).unwrap(),
+            ["[1.0, 2.0] [1.0, 2.0]", "[[1.0], [2.0, 3.5]]", "ab"]
+        );
+    }
+
+    expect_error(
+        "x : Real[] <- [1, 2]\nOUTPUT x[0] DIV 1\n",
+        "Mismatched Type",
+    );
+}
+
+#[test]
+fn element_stores_follow_the_declared_type() {
+    let source = "x : Real[] <- [1, 2]\nx[0] <- 5\nm : Integer[][] <- [[1], [2]]\nm[1][0] <- 7\nOUTPUT x, ' ', m\n";
+
+    for backend in BACKENDS {
+        assert_eq!(run(source, backend).unwrap(), ["[5.0, 2.0] [[1], [7]]"]);
+    }
+
+    expect_error(
+        "x : Integer[] <- [1, 2]\nx[0] <- 'a'\n",
+        "at line 2, column 1: Expected Integer for 'x', found String",
+    );
+    expect_error(
+        "x : Integer[][] <- [[1]]\nx[0][0] <- 'a'\n",
+        "Expected Integer for 'x', found String",
+    );
+    expect_error(
+        "RECORD P\n\tv : Integer[]\nENDRECORD\np <- P([1])\np.v[0] <- 'x'\n",
+        "at line 5, column 1: Expected Integer for 'v', found String",
+    );
+    expect_error(
+        "SUBROUTINE f(a : Boolean[])\n\ta[0] <- 1\nENDSUBROUTINE\nf([True])\n",
+        "Expected Boolean for 'a', found Integer",
+    );
+}
+
+#[test]
+fn unknown_types_are_rejected_before_running() {
+    expect_error(
+        "OUTPUT 'first'\nx : integer <- 1\n",
+        "at line 2, column 1: Unknown type 'integer'",
+    );
+    expect_error(
+        "SUBROUTINE f(n : Foo[])\nENDSUBROUTINE\n",
+        "at line 1, column 14: Unknown type 'Foo'",
+    );
+
+    let source = "p : Point <- Point(1)\nRECORD Point\n\tx : Integer\nENDRECORD\nOUTPUT p\n";
+    for backend in BACKENDS {
+        assert_eq!(run(source, backend).unwrap(), ["Point(x: 1)"]);
+    }
+}
+
+#[test]
+fn slash_is_real_division() {
+    let source = "OUTPUT 7 / 2, ' ', 7 DIV 2, ' ', 6 / 3, ' ', -7 MOD 2\n";
+
+    for backend in BACKENDS {
+        assert_eq!(run(source, backend).unwrap(), ["3.5 3 2.0 -1"]);
+    }
+
+    expect_error("OUTPUT 1 / 0\n", "Division by zero");
+    expect_error("OUTPUT 1 DIV 0\n", "Division by zero");
+}
+
+#[test]
+fn escaped_strings() {
+    let source =
+        "OUTPUT 'a\\nb'\nOUTPUT 'it\\'s', \"\\\"\", 'back\\\\slash', CHAR_TO_CODE(\"\\n\")\n";
+
+    for backend in BACKENDS {
+        assert_eq!(
+            run(source, backend).unwrap(),
+            ["a\nb", "it's\"back\\slash10"]
+        );
+    }
+
+    expect_error("OUTPUT 'open\\'\n", "Unexpected EOF");
+}
+
+#[test]
+fn userinput_after_the_input_runs_out() {
+    expect_error("x <- USERINPUT\n", "No more input available for USERINPUT");
+
+    for backend in BACKENDS {
+        let mut output = Vec::new();
+        let mut input = VecDeque::from([String::from("first")]);
+        let options = RunOptions {
+            backend,
+            ..RunOptions::default()
+        };
+        let error = aqa::run(
+            String::from("OUTPUT USERINPUT\nOUTPUT USERINPUT\n"),
+            &mut output,
+            &mut input,
+            &options,
+        )
+        .unwrap_err();
+
+        assert_eq!(output, ["first"], "{:?}", backend);
+        assert!(
+            error
+                .to_string()
+                .ends_with("No more input available for USERINPUT"),
+            "{:?} reported '{}'",
+            backend,
+            error
+        );
+    }
+}
+
+#[test]
+fn substring_includes_both_ends() {
+    for backend in BACKENDS {
+        assert_eq!(
+            run(
+                "OUTPUT SUBSTRING(2, 9, 'computer science')\nOUTPUT SUBSTRING(0, 0, 'abc')\nOUTPUT SUBSTRING(2, 1, 'abc') = ''\n",
+                backend
+            )
+            .unwrap(),
+            ["mputer s", "a", "True"]
+        );
+    }
+
+    expect_error(
+        "OUTPUT SUBSTRING(1, 3, 'abc')\n",
+        "Index 3 is out of bounds for length 3",
+    );
+    expect_error(
+        "OUTPUT SUBSTRING(-1, 1, 'abc')\n",
+        "Index -1 is out of bounds for length 3",
+    );
+}
+
+#[test]
+fn conversions_between_strings_and_numbers() {
+    for backend in BACKENDS {
+        assert_eq!(
+            run(
+                "OUTPUT STRING_TO_INT('-42') + 1\nOUTPUT STRING_TO_REAL('2.5') * 2\nOUTPUT INT_TO_STRING(7) + '!'\nOUTPUT REAL_TO_STRING(1.5) + '!'\n",
+                backend
+            )
+            .unwrap(),
+            ["-41", "5.0", "7!", "1.5!"]
+        );
+    }
+
+    expect_error(
+        "OUTPUT STRING_TO_INT('1.5')\n",
+        "Cannot convert '1.5' to Integer",
+    );
+    expect_error(
+        "OUTPUT STRING_TO_REAL('abc')\n",
+        "Cannot convert 'abc' to Real",
+    );
+}
+
+#[test]
+fn reals_survive_a_round_trip_through_strings() {
+    for backend in BACKENDS {
+        assert_eq!(
+            run(
+                "FOR x IN [0.1, 2.0, -3.75, 1.0 / 3.0]\n\tOUTPUT STRING_TO_REAL(REAL_TO_STRING(x)) = x\nENDFOR\n",
+                backend
+            )
+            .unwrap(),
+            ["True", "True", "True", "True"]
+        );
+    }
+}
+
+#[test]
+fn dumps_are_only_kept_when_requested() {
+    let source = "x <- 1\nOUTPUT x + 1\n";
+
+    for backend in BACKENDS {
+        let run = aqa::run(
+            source.to_string(),
+            &mut Vec::new(),
+            &mut VecDeque::new(),
+            &RunOptions {
+                backend,
+                ..RunOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(run.output, ["2"]);
+        assert!(run.tokens.is_none() && run.ast.is_none() && run.trace.is_none());
+
+        let run = aqa::run(
+            source.to_string(),
+            &mut Vec::new(),
+            &mut VecDeque::new(),
+            &RunOptions {
+                dump_tokens: true,
+                dump_ast: true,
+                trace: true,
+                backend,
+                ..RunOptions::default()
+            },
+        )
+        .unwrap();
+        let tokens: Vec<TokenType> = run
+            .tokens
+            .unwrap()
+            .into_iter()
+            .map(|token| token.token_type)
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                TokenType::Identifier(String::from("x")),
+                TokenType::Assign,
+                TokenType::Literal(Value::Int(1)),
+                TokenType::LineBreak,
+                TokenType::Output,
+                TokenType::Identifier(String::from("x")),
+                TokenType::Add,
+                TokenType::Literal(Value::Int(1)),
+                TokenType::LineBreak,
+                TokenType::Eof,
+            ]
+        );
+        let ast = run.ast.unwrap();
+        assert!(ast.starts_with("[\n    Stmt {\n        stmt_type: Assign("));
+        assert!(ast.contains("stmt_type: Output("));
+        assert_eq!(run.trace.unwrap(), ["1:1: x <- 1", "2:1: OUTPUT x + 1"]);
+    }
+}
+
+#[test]
+fn interpreter_state_carries_across_calls() {
+    let mut output = Vec::new();
+    let mut input = VecDeque::new();
+    let mut interpreter = aqa::Interpreter::new(&mut output, &mut input);
+
+    let first = aqa::parse(String::from(
+        "RECORD P\n\tx: Integer\nENDRECORD\nSUBROUTINE double(n)\n\tRETURN n * 2\nENDSUBROUTINE\ncount <- 1\np <- P(4)\n",
+    ))
+    .unwrap();
+    assert_eq!(interpreter.execute(&first).unwrap(), None);
+
+    let second = aqa::parse(String::from(
+        "count <- count + 1\nq <- P(double(p.x))\nq.x\n",
+    ))
+    .unwrap();
+    assert_eq!(interpreter.execute(&second).unwrap(), Some(Value::Int(8)));
+
+    let step = aqa::parse(String::from("OUTPUT count\ncount * 10\n")).unwrap();
+    let values: Vec<Option<Value>> = step
+        .statements()
+        .iter()
+        .map(|stmt| interpreter.execute_statement(stmt).unwrap())
+        .collect();
+    assert_eq!(values, [None, Some(Value::Int(20))]);
+
+    let names: Vec<String> = interpreter
+        .globals()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(names, ["count", "p", "q"]);
+
+    interpreter.reset();
+    assert!(interpreter.globals().is_empty());
+    let error = interpreter
+        .execute(&aqa::parse(String::from("OUTPUT count\n")).unwrap())
+        .unwrap_err();
+    assert!(
+        error.to_string().ends_with("Undefined variable 'count'"),
+        "{}",
+        error
+    );
+    assert!(interpreter
+        .execute(&aqa::parse(String::from("double(1)\n")).unwrap())
+        .is_err());
+
+    drop(interpreter);
+    assert_eq!(output, ["2"]);
+}
+
+#[test]
+fn fields_a_record_does_not_declare() {
+    let record = "RECORD P\n\ty: Integer\nENDRECORD\np <- P(1)\n";
+
+    expect_error(
+        &format!("{}OUTPUT p.x\n", record),
+        "Record 'P' has no field 'x'",
+    );
+    expect_error(
+        &format!("{}p.x <- 2\n", record),
+        "Record 'P' has no field 'x'",
+    );
+}
@@ -0,0 +1,83 @@
+fn messages(source: &str) -> Vec<String> {
+    aqa::check(source.to_string())
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect()
+}
+
+#[test]
+fn block_errors_name_keywords() {
+    assert_eq!(
+        messages("WHILE TRUE\n\tOUTPUT 1\nENDFOR\n"),
+        ["'ENDFOR' cannot close 'WHILE', expected 'ENDWHILE'"]
+    );
+    assert_eq!(
+        messages("IF TRUE THEN\n\tOUTPUT 1\n"),
+        ["'IF' is never closed, expected 'ENDIF'"]
+    );
+}
+
+#[test]
+fn messages_describe_tokens_as_written() {
+    assert_eq!(messages("x <- 1 2\n"), ["Expected end of line, found '2'"]);
+    assert_eq!(messages("x <- 1 +\n"), ["Unexpected end of line"]);
+    assert_eq!(
+        messages("RECORD P\n\ta Integer\nENDRECORD\n"),
+        ["Expected ':', found 'Integer'"]
+    );
+    assert_eq!(messages("OUTPUT (1\n"), ["Expected ')'"]);
+    assert_eq!(
+        messages("OUTPUT 'a' \"b\"\n"),
+        ["Expected end of line, found \"b\""]
+    );
+}
+
+#[test]
+fn every_error_in_a_file_is_reported() {
+    assert_eq!(
+        messages("x <- 1 2\nOUTPUT (1\ny <-\nOUTPUT 3\n"),
+        [
+            "Expected end of line, found '2'",
+            "Expected ')'",
+            "Unexpected end of line"
+        ]
+    );
+}
+
+#[test]
+fn mistyped_terminator_in_nested_blocks() {
+    assert_eq!(
+        messages("WHILE TRUE\n\tIF x THEN\n\t\tFOR i <- 1 TO 3\n\t\t\tOUTPUT i\n\t\tENDWHILE\n\tENDIF\nENDWHILE\n"),
+        ["'ENDWHILE' cannot close 'FOR', expected 'ENDFOR'"]
+    );
+}
+
+#[test]
+fn missing_terminators_in_nested_blocks() {
+    assert_eq!(
+        messages(
+            "WHILE TRUE\n\tIF x THEN\n\t\tFOR i <- 1 TO 3\n\t\t\tOUTPUT i\nENDWHILE\nOUTPUT 1\n"
+        ),
+        ["'ENDWHILE' cannot close 'FOR', expected 'ENDFOR'"]
+    );
+    assert_eq!(
+        messages("IF a THEN\n\tWHILE b\n\t\tOUTPUT 1\nELSE\n\tOUTPUT 2\nENDIF\n"),
+        ["'ELSE' cannot close 'WHILE', expected 'ENDWHILE'"]
+    );
+}
+
+#[test]
+fn broken_header_still_checks_its_body() {
+    assert_eq!(
+        messages("IF x x THEN\n\tOUTPUT (1\nENDIF\nOUTPUT 2 3\n"),
+        [
+            "Expected 'THEN', found 'x'",
+            "Expected ')'",
+            "Expected end of line, found '3'"
+        ]
+    );
+    assert_eq!(
+        messages("SUBROUTINE f(a b)\n\tRETURN 1 +\nENDSUBROUTINE\n"),
+        ["Expected ')', found 'b'", "Unexpected end of line"]
+    );
+}